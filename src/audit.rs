@@ -0,0 +1,187 @@
+use std::{fmt, ops::RangeInclusive};
+
+use alloy_primitives::B256;
+use trin_validation::accumulator::PreMergeAccumulator;
+
+use crate::{
+    epoch::FINAL_EPOCH,
+    errors::SyncError,
+    sync::{EpochRecord, Lock},
+};
+
+/// An epoch whose recorded root differs from the pre-merge accumulator
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RootMismatch {
+    pub epoch: usize,
+    pub stored: [u8; 32],
+    pub expected: [u8; 32],
+}
+
+/// The result of checking recorded sync state against a [`PreMergeAccumulator`],
+/// see [`audit_lock`]
+#[derive(Debug, Default)]
+pub struct AuditReport {
+    /// Epochs whose recorded root matches the accumulator
+    pub verified: Vec<usize>,
+    /// Epochs whose recorded root differs from the accumulator
+    pub mismatched: Vec<RootMismatch>,
+    /// Recorded epochs after [`FINAL_EPOCH`], which the accumulator does not cover
+    pub out_of_range: Vec<usize>,
+    /// Pre-merge epochs without a record
+    pub missing: Vec<usize>,
+    /// Entries that could not be decoded
+    pub invalid: Vec<SyncError>,
+}
+
+impl AuditReport {
+    /// Returns `true` if every record matches the accumulator, regardless of coverage
+    pub fn is_consistent(&self) -> bool {
+        self.mismatched.is_empty() && self.out_of_range.is_empty() && self.invalid.is_empty()
+    }
+
+    /// Returns `true` if every pre-merge epoch is recorded
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    /// Returns the missing epochs grouped into contiguous ranges
+    pub fn gaps(&self) -> Vec<RangeInclusive<usize>> {
+        let mut gaps: Vec<RangeInclusive<usize>> = Vec::new();
+        for &epoch in &self.missing {
+            match gaps.last_mut() {
+                Some(gap) if *gap.end() + 1 == epoch => *gap = *gap.start()..=epoch,
+                _ => gaps.push(epoch..=epoch),
+            }
+        }
+        gaps
+    }
+}
+
+impl fmt::Display for AuditReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "verified epochs: {}", self.verified.len())?;
+        for mismatch in &self.mismatched {
+            writeln!(
+                f,
+                "epoch {}: root mismatch, stored {} but the accumulator has {}",
+                mismatch.epoch,
+                B256::from(mismatch.stored),
+                B256::from(mismatch.expected)
+            )?;
+        }
+        for epoch in &self.out_of_range {
+            writeln!(
+                f,
+                "epoch {}: out of range, the last epoch is {}",
+                epoch, FINAL_EPOCH
+            )?;
+        }
+        for error in &self.invalid {
+            writeln!(f, "invalid entry: {}", error)?;
+        }
+        for gap in self.gaps() {
+            if gap.start() == gap.end() {
+                writeln!(f, "missing epoch {}", gap.start())?;
+            } else {
+                writeln!(f, "missing epochs {} - {}", gap.start(), gap.end())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks every entry of `lock` against `pre_merge_accumulator`, without re-validating
+/// any header.
+pub fn audit_lock(lock: &Lock, pre_merge_accumulator: &PreMergeAccumulator) -> AuditReport {
+    let (records, invalid) = lock.partition_records();
+    AuditReport {
+        invalid,
+        ..audit_records(&records, pre_merge_accumulator)
+    }
+}
+
+/// Checks `records` against `pre_merge_accumulator`, e.g. the records listed by a
+/// [`crate::sync::SyncStore`].
+pub fn audit_records(
+    records: &[EpochRecord],
+    pre_merge_accumulator: &PreMergeAccumulator,
+) -> AuditReport {
+    let mut report = AuditReport::default();
+    let mut recorded = vec![false; FINAL_EPOCH + 1];
+
+    for record in records {
+        let expected = match pre_merge_accumulator.historical_epochs.get(record.epoch) {
+            Some(expected) if record.epoch <= FINAL_EPOCH => expected.0,
+            _ => {
+                report.out_of_range.push(record.epoch);
+                continue;
+            }
+        };
+
+        recorded[record.epoch] = true;
+        if record.root == expected {
+            report.verified.push(record.epoch);
+        } else {
+            report.mismatched.push(RootMismatch {
+                epoch: record.epoch,
+                stored: record.root,
+                expected,
+            });
+        }
+    }
+
+    report.missing = (0..=FINAL_EPOCH)
+        .filter(|&epoch| !recorded[epoch])
+        .collect();
+    report.verified.sort_unstable();
+    report.mismatched.sort_by_key(|mismatch| mismatch.epoch);
+    report.out_of_range.sort_unstable();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::LockEntry;
+
+    #[test]
+    fn test_audit_lock() {
+        let acc = PreMergeAccumulator::default();
+        let mut lock = Lock::new();
+        for epoch in 0..=FINAL_EPOCH {
+            if (10..20).contains(&epoch) || epoch == 30 {
+                continue;
+            }
+            lock.update(LockEntry::new(&epoch, acc.historical_epochs[epoch].0));
+        }
+        lock.update(LockEntry::new(&5, [0; 32]));
+        lock.update(LockEntry::new(&(FINAL_EPOCH + 1), [0; 32]));
+
+        let report = audit_lock(&lock, &acc);
+
+        assert!(!report.is_consistent());
+        assert!(!report.is_complete());
+        assert_eq!(report.verified.len(), FINAL_EPOCH + 1 - 12);
+        assert_eq!(report.mismatched.len(), 1);
+        assert_eq!(report.mismatched[0].epoch, 5);
+        assert_eq!(report.mismatched[0].expected, acc.historical_epochs[5].0);
+        assert_eq!(report.out_of_range, vec![FINAL_EPOCH + 1]);
+        assert!(report.invalid.is_empty());
+        assert_eq!(report.gaps(), vec![10..=19, 30..=30]);
+    }
+
+    #[test]
+    fn test_audit_complete_lock() {
+        let acc = PreMergeAccumulator::default();
+        let mut lock = Lock::new();
+        for epoch in 0..=FINAL_EPOCH {
+            lock.update(LockEntry::new(&epoch, acc.historical_epochs[epoch].0));
+        }
+
+        let report = audit_lock(&lock, &acc);
+
+        assert!(report.is_consistent());
+        assert!(report.is_complete());
+        assert!(report.gaps().is_empty());
+    }
+}
@@ -0,0 +1,273 @@
+use alloy_primitives::{keccak256, B256};
+use ethportal_api::Header;
+use rlp::RlpStream;
+use sf_protos::ethereum::r#type::v2::{
+    BigInt, Block, BlockHeader, TransactionReceipt, TransactionTrace,
+};
+
+use crate::{errors::EraValidateError, trie::ordered_trie_root};
+
+/// Chain id of Ethereum mainnet, signed into typed transactions
+pub const MAINNET_CHAIN_ID: u64 = 1;
+
+const LEGACY_TRANSACTION: i32 = 0;
+const ACCESS_LIST_TRANSACTION: i32 = 1;
+const DYNAMIC_FEE_TRANSACTION: i32 = 2;
+const BLOB_TRANSACTION: i32 = 3;
+
+/// `TransactionTraceStatus::Succeeded` of Firehose
+const TRANSACTION_SUCCEEDED: i32 = 1;
+
+const MAX_UNCLES: usize = 2;
+/// Uncles must be at most this many generations older than the block including them
+const MAX_UNCLE_DEPTH: u64 = 6;
+
+/// Checks the transactions, receipts and uncles of a Firehose block against the transactions
+/// root, receipts root and uncles hash of its header, so that the content of the block is as
+/// canonical as its header.
+///
+/// Typed transactions sign the chain id, which Firehose does not record, so it must be given.
+pub fn verify_body(block: &Block, chain_id: u64) -> Result<(), EraValidateError> {
+    let header = block
+        .header
+        .as_ref()
+        .ok_or(EraValidateError::HeaderDecodeError)?;
+
+    if transactions_root(block, chain_id)?.as_slice() != header.transactions_root {
+        return Err(EraValidateError::TransactionsRootMismatch(block.number));
+    }
+    if receipts_root(block)?.as_slice() != header.receipt_root {
+        return Err(EraValidateError::ReceiptsRootMismatch(block.number));
+    }
+    verify_uncles(block)?;
+
+    Ok(())
+}
+
+/// Checks that `block` has at most two uncles, each at most six generations older than the
+/// block, and that they hash to the uncles hash of its header.
+pub fn verify_uncles(block: &Block) -> Result<(), EraValidateError> {
+    let header = block
+        .header
+        .as_ref()
+        .ok_or(EraValidateError::HeaderDecodeError)?;
+
+    if block.uncles.len() > MAX_UNCLES {
+        return Err(EraValidateError::TooManyUncles(block.number));
+    }
+    for uncle in &block.uncles {
+        if uncle.number >= block.number || uncle.number + MAX_UNCLE_DEPTH < block.number {
+            return Err(EraValidateError::InvalidUncleDepth(
+                block.number,
+                uncle.number,
+            ));
+        }
+    }
+
+    if uncles_hash(block)?.as_slice() != header.uncle_hash {
+        return Err(EraValidateError::UnclesHashMismatch(block.number));
+    }
+
+    Ok(())
+}
+
+/// Computes the hash of the RLP list of the uncle headers of `block`
+pub fn uncles_hash(block: &Block) -> Result<B256, EraValidateError> {
+    let uncles = block
+        .uncles
+        .iter()
+        .map(uncle_header)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(keccak256(rlp::encode_list(&uncles)))
+}
+
+/// Converts a Firehose uncle through the conversion of the block it would head. Uncles are
+/// pre-merge headers, whose hash covers their proof of work seal.
+fn uncle_header(uncle: &BlockHeader) -> Result<Header, EraValidateError> {
+    let block = Block {
+        hash: uncle.hash.clone(),
+        number: uncle.number,
+        header: Some(uncle.clone()),
+        ..Default::default()
+    };
+    let header = Header::try_from(&block)?;
+    if header.mix_hash.is_none() || header.nonce.is_none() {
+        return Err(EraValidateError::MissingSeal(uncle.number));
+    }
+    Ok(header)
+}
+
+/// Rebuilds the transactions trie of `block` from its transaction traces
+pub fn transactions_root(block: &Block, chain_id: u64) -> Result<B256, EraValidateError> {
+    let transactions = transaction_traces(block)
+        .iter()
+        .map(|trace| encode_transaction(trace, chain_id, block.number))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(ordered_trie_root(&transactions))
+}
+
+/// Rebuilds the receipts trie of `block` from the receipts of its transaction traces
+pub fn receipts_root(block: &Block) -> Result<B256, EraValidateError> {
+    let receipts = transaction_traces(block)
+        .iter()
+        .map(|trace| encode_receipt(trace, block.number))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(ordered_trie_root(&receipts))
+}
+
+/// Firehose records the allocation of the genesis block as a transaction trace, which is not
+/// one of its transactions
+fn transaction_traces(block: &Block) -> &[TransactionTrace] {
+    if block.number == 0 {
+        return &[];
+    }
+    &block.transaction_traces
+}
+
+/// Returns the consensus encoding of a transaction: its RLP for legacy transactions, and its
+/// type followed by the RLP of its payload for typed ones. Blob transactions are encoded without
+/// their sidecar, as they are included in blocks.
+pub fn encode_transaction(
+    trace: &TransactionTrace,
+    chain_id: u64,
+    block_number: u64,
+) -> Result<Vec<u8>, EraValidateError> {
+    let mut stream = RlpStream::new();
+    match trace.r#type {
+        LEGACY_TRANSACTION => {
+            stream.begin_list(9);
+            stream.append(&trace.nonce);
+            append_big_int(&mut stream, trace.gas_price.as_ref());
+            stream.append(&trace.gas_limit);
+            stream.append(&trace.to);
+            append_big_int(&mut stream, trace.value.as_ref());
+            stream.append(&trace.input);
+        }
+        ACCESS_LIST_TRANSACTION => {
+            stream.begin_list(11);
+            stream.append(&chain_id);
+            stream.append(&trace.nonce);
+            append_big_int(&mut stream, trace.gas_price.as_ref());
+            stream.append(&trace.gas_limit);
+            stream.append(&trace.to);
+            append_big_int(&mut stream, trace.value.as_ref());
+            stream.append(&trace.input);
+            append_access_list(&mut stream, trace);
+        }
+        DYNAMIC_FEE_TRANSACTION => {
+            stream.begin_list(12);
+            stream.append(&chain_id);
+            stream.append(&trace.nonce);
+            append_big_int(&mut stream, trace.max_priority_fee_per_gas.as_ref());
+            append_big_int(&mut stream, trace.max_fee_per_gas.as_ref());
+            stream.append(&trace.gas_limit);
+            stream.append(&trace.to);
+            append_big_int(&mut stream, trace.value.as_ref());
+            stream.append(&trace.input);
+            append_access_list(&mut stream, trace);
+        }
+        BLOB_TRANSACTION => {
+            stream.begin_list(14);
+            stream.append(&chain_id);
+            stream.append(&trace.nonce);
+            append_big_int(&mut stream, trace.max_priority_fee_per_gas.as_ref());
+            append_big_int(&mut stream, trace.max_fee_per_gas.as_ref());
+            stream.append(&trace.gas_limit);
+            stream.append(&trace.to);
+            append_big_int(&mut stream, trace.value.as_ref());
+            stream.append(&trace.input);
+            append_access_list(&mut stream, trace);
+            append_big_int(&mut stream, trace.blob_gas_fee_cap.as_ref());
+            stream.begin_list(trace.blob_hashes.len());
+            for blob_hash in &trace.blob_hashes {
+                stream.append(blob_hash);
+            }
+        }
+        transaction_type => {
+            return Err(EraValidateError::UnsupportedTransactionType(
+                block_number,
+                transaction_type,
+            ))
+        }
+    }
+    append_uint(&mut stream, &trace.v);
+    append_uint(&mut stream, &trace.r);
+    append_uint(&mut stream, &trace.s);
+
+    Ok(with_type(trace.r#type, stream))
+}
+
+/// Returns the consensus encoding of the receipt of a transaction, typed like the transaction.
+/// Receipts carry the post-transaction state root before Byzantium, and a status after.
+pub fn encode_receipt(
+    trace: &TransactionTrace,
+    block_number: u64,
+) -> Result<Vec<u8>, EraValidateError> {
+    let receipt: &TransactionReceipt = trace
+        .receipt
+        .as_ref()
+        .ok_or(EraValidateError::MissingReceipt(block_number))?;
+
+    let mut stream = RlpStream::new_list(4);
+    if receipt.state_root.is_empty() {
+        stream.append(&u8::from(trace.status == TRANSACTION_SUCCEEDED));
+    } else {
+        stream.append(&receipt.state_root);
+    }
+    stream.append(&receipt.cumulative_gas_used);
+    stream.append(&receipt.logs_bloom);
+    stream.begin_list(receipt.logs.len());
+    for log in &receipt.logs {
+        stream.begin_list(3);
+        stream.append(&log.address);
+        stream.begin_list(log.topics.len());
+        for topic in &log.topics {
+            stream.append(topic);
+        }
+        stream.append(&log.data);
+    }
+
+    Ok(with_type(trace.r#type, stream))
+}
+
+fn with_type(transaction_type: i32, stream: RlpStream) -> Vec<u8> {
+    let payload = stream.out();
+    if transaction_type == LEGACY_TRANSACTION {
+        return payload.to_vec();
+    }
+
+    let mut encoded = Vec::with_capacity(payload.len() + 1);
+    encoded.push(transaction_type as u8);
+    encoded.extend_from_slice(&payload);
+    encoded
+}
+
+fn append_access_list(stream: &mut RlpStream, trace: &TransactionTrace) {
+    stream.begin_list(trace.access_list.len());
+    for tuple in &trace.access_list {
+        stream.begin_list(2);
+        stream.append(&tuple.address);
+        stream.begin_list(tuple.storage_keys.len());
+        for storage_key in &tuple.storage_keys {
+            stream.append(storage_key);
+        }
+    }
+}
+
+fn append_big_int(stream: &mut RlpStream, value: Option<&BigInt>) {
+    append_uint(
+        stream,
+        value
+            .map(|value| value.bytes.as_slice())
+            .unwrap_or_default(),
+    );
+}
+
+/// Firehose may pad big-endian integers, while RLP encodes them without leading zeros
+fn append_uint(stream: &mut RlpStream, bytes: &[u8]) {
+    let start = bytes
+        .iter()
+        .position(|byte| *byte != 0)
+        .unwrap_or(bytes.len());
+    stream.append(&&bytes[start..]);
+}
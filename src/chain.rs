@@ -0,0 +1,87 @@
+use alloy_primitives::B256;
+
+use crate::{errors::EraValidateError, types::ExtHeaderRecord};
+
+/// Checks that headers form a chain: block numbers follow each other without duplicates or
+/// gaps, and every header's parent hash is the hash of the previous header.
+///
+/// Unlike the epoch roots of the pre-merge accumulator, this does not depend on any
+/// reference, so it also applies to post-merge blocks. Headers must include their
+/// full header.
+#[derive(Clone, Debug, Default)]
+pub struct ChainValidator {
+    tip: Option<(u64, B256)>,
+}
+
+impl ChainValidator {
+    pub fn new() -> Self {
+        ChainValidator::default()
+    }
+
+    /// Continues the chain from an already validated block, e.g. the last block of the
+    /// previous epoch, so that consecutive ranges are checked to join up.
+    pub fn with_anchor(block_number: u64, block_hash: B256) -> Self {
+        ChainValidator {
+            tip: Some((block_number, block_hash)),
+        }
+    }
+
+    /// The number and hash of the last header of the chain
+    pub fn tip(&self) -> Option<(u64, B256)> {
+        self.tip
+    }
+
+    /// Adds the next header of the chain. A header that does not extend the chain is rejected
+    /// and the chain is left as it was.
+    pub fn push(&mut self, header: &ExtHeaderRecord) -> Result<(), EraValidateError> {
+        let full_header = header
+            .full_header
+            .as_ref()
+            .ok_or(EraValidateError::ExtHeaderRecordError)?;
+
+        if let Some((tip_number, tip_hash)) = self.tip {
+            let expected = tip_number + 1;
+            if header.block_number == tip_number {
+                return Err(EraValidateError::DuplicateBlockNumber(header.block_number));
+            }
+            if header.block_number > expected {
+                return Err(EraValidateError::MissingBlockNumbers(
+                    expected,
+                    header.block_number - 1,
+                ));
+            }
+            if header.block_number < expected {
+                return Err(EraValidateError::UnexpectedBlockNumber(
+                    expected,
+                    header.block_number,
+                ));
+            }
+            if full_header.parent_hash != tip_hash {
+                return Err(EraValidateError::ParentHashMismatch(header.block_number));
+            }
+        }
+
+        self.tip = Some((header.block_number, full_header.hash()));
+        Ok(())
+    }
+}
+
+/// Checks that `headers` form a chain, see [`ChainValidator`]. If `anchor` is set, the first
+/// header must be its child.
+///
+/// Returns the number and hash of the last header, to anchor the next range.
+pub fn validate_chain(
+    headers: &[ExtHeaderRecord],
+    anchor: Option<(u64, B256)>,
+) -> Result<Option<(u64, B256)>, EraValidateError> {
+    let mut validator = match anchor {
+        Some((block_number, block_hash)) => ChainValidator::with_anchor(block_number, block_hash),
+        None => ChainValidator::new(),
+    };
+
+    for header in headers {
+        validator.push(header)?;
+    }
+
+    Ok(validator.tip())
+}
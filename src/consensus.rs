@@ -0,0 +1,345 @@
+use alloy_primitives::{b256, B256, U256};
+use ethportal_api::Header;
+
+use crate::{errors::EraValidateError, types::ExtHeaderRecord};
+
+/// Hash of an empty list of uncles
+const EMPTY_UNCLES_HASH: B256 =
+    b256!("1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347");
+
+const MINIMUM_DIFFICULTY: u64 = 131072;
+const DIFFICULTY_BOUND_DIVISOR: u64 = 2048;
+const EXP_DIFF_PERIOD: u64 = 100000;
+const MIN_GAS_LIMIT: u64 = 5000;
+const GAS_LIMIT_BOUND_DIVISOR: u64 = 1024;
+const MAXIMUM_EXTRA_DATA_SIZE: usize = 32;
+const ELASTICITY_MULTIPLIER: u64 = 2;
+const BASE_FEE_CHANGE_DENOMINATOR: u64 = 8;
+const INITIAL_BASE_FEE: u64 = 1_000_000_000;
+
+const DAO_FORK_BLOCK: u64 = 1_920_000;
+const DAO_FORK_EXTRA_DATA: &[u8] = b"dao-hard-fork";
+const DAO_FORK_EXTRA_DATA_RANGE: u64 = 10;
+
+/// The mainnet hard forks that changed the rules of block headers
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Fork {
+    Frontier,
+    Homestead,
+    Byzantium,
+    Constantinople,
+    MuirGlacier,
+    London,
+    ArrowGlacier,
+    GrayGlacier,
+    Paris,
+}
+
+impl Fork {
+    /// Returns the fork whose rules apply to the block `block_number`
+    pub fn at(block_number: u64) -> Fork {
+        match block_number {
+            0..=1_149_999 => Fork::Frontier,
+            1_150_000..=4_369_999 => Fork::Homestead,
+            4_370_000..=7_279_999 => Fork::Byzantium,
+            7_280_000..=9_199_999 => Fork::Constantinople,
+            9_200_000..=12_964_999 => Fork::MuirGlacier,
+            12_965_000..=13_772_999 => Fork::London,
+            13_773_000..=15_049_999 => Fork::ArrowGlacier,
+            15_050_000..=15_537_393 => Fork::GrayGlacier,
+            _ => Fork::Paris,
+        }
+    }
+
+    /// Number of blocks the difficulty bomb is delayed by
+    fn bomb_delay(&self) -> u64 {
+        match self {
+            Fork::Frontier | Fork::Homestead => 0,
+            Fork::Byzantium => 3_000_000,
+            Fork::Constantinople => 5_000_000,
+            Fork::MuirGlacier => 9_000_000,
+            Fork::London => 9_700_000,
+            Fork::ArrowGlacier => 10_700_000,
+            Fork::GrayGlacier | Fork::Paris => 11_400_000,
+        }
+    }
+}
+
+/// Returns the difficulty required of the block `block_number` mined at `timestamp` on top
+/// of `parent`.
+pub fn expected_difficulty(parent: &Header, block_number: u64, timestamp: u64) -> U256 {
+    difficulty(
+        Fork::at(block_number),
+        parent.difficulty,
+        parent.uncles_hash != EMPTY_UNCLES_HASH,
+        timestamp.saturating_sub(parent.timestamp),
+        block_number,
+    )
+}
+
+fn difficulty(
+    fork: Fork,
+    parent_difficulty: U256,
+    parent_has_uncles: bool,
+    time_delta: u64,
+    block_number: u64,
+) -> U256 {
+    if fork == Fork::Paris {
+        return U256::ZERO;
+    }
+
+    let factor: i64 = match fork {
+        Fork::Frontier if time_delta < 13 => 1,
+        Fork::Frontier => -1,
+        Fork::Homestead => (1 - (time_delta / 10) as i64).max(-99),
+        _ => {
+            let uncles = if parent_has_uncles { 2 } else { 1 };
+            (uncles - (time_delta / 9) as i64).max(-99)
+        }
+    };
+
+    let adjustment = parent_difficulty / U256::from(DIFFICULTY_BOUND_DIVISOR)
+        * U256::from(factor.unsigned_abs());
+    let difficulty = if factor >= 0 {
+        parent_difficulty + adjustment
+    } else {
+        parent_difficulty.saturating_sub(adjustment)
+    };
+    let mut difficulty = difficulty.max(U256::from(MINIMUM_DIFFICULTY));
+
+    // the difficulty bomb, delayed by later forks
+    let period = block_number.saturating_sub(fork.bomb_delay()) / EXP_DIFF_PERIOD;
+    if period > 1 {
+        difficulty += U256::from(1) << (period - 2) as usize;
+    }
+
+    difficulty
+}
+
+/// Returns the EIP-1559 base fee of the child of `parent`, which must be a London block or
+/// later, or its parent.
+pub fn expected_base_fee(parent: &Header) -> U256 {
+    match parent.base_fee_per_gas {
+        Some(parent_base_fee) => base_fee(parent.gas_limit, parent.gas_used, parent_base_fee),
+        None => U256::from(INITIAL_BASE_FEE),
+    }
+}
+
+fn base_fee(parent_gas_limit: U256, parent_gas_used: U256, parent_base_fee: U256) -> U256 {
+    let gas_target = parent_gas_limit / U256::from(ELASTICITY_MULTIPLIER);
+    let denominator = U256::from(BASE_FEE_CHANGE_DENOMINATOR);
+
+    if gas_target.is_zero() || parent_gas_used == gas_target {
+        parent_base_fee
+    } else if parent_gas_used > gas_target {
+        let delta = parent_base_fee * (parent_gas_used - gas_target) / gas_target / denominator;
+        parent_base_fee + delta.max(U256::from(1))
+    } else {
+        let delta = parent_base_fee * (gas_target - parent_gas_used) / gas_target / denominator;
+        parent_base_fee.saturating_sub(delta)
+    }
+}
+
+/// Checks the consensus rules of `header` that depend on its `parent`: difficulty, gas limit,
+/// timestamp, extra data and base fee, according to the fork of `header`.
+pub fn check_header_rules(parent: &Header, header: &Header) -> Result<(), EraValidateError> {
+    let number = header.number;
+    let fork = Fork::at(number);
+
+    if number != parent.number + 1 {
+        return Err(EraValidateError::UnexpectedBlockNumber(
+            parent.number + 1,
+            number,
+        ));
+    }
+
+    if header.timestamp <= parent.timestamp {
+        return Err(EraValidateError::InvalidTimestamp(number));
+    }
+
+    let dao_extra_data = (DAO_FORK_BLOCK..DAO_FORK_BLOCK + DAO_FORK_EXTRA_DATA_RANGE)
+        .contains(&number)
+        && header.extra_data != DAO_FORK_EXTRA_DATA;
+    if header.extra_data.len() > MAXIMUM_EXTRA_DATA_SIZE || dao_extra_data {
+        return Err(EraValidateError::InvalidExtraData(number));
+    }
+
+    let expected = expected_difficulty(parent, number, header.timestamp);
+    if header.difficulty != expected {
+        log::error!(
+            "the difficulty of block {} should be {} but is {}",
+            number,
+            expected,
+            header.difficulty
+        );
+        return Err(EraValidateError::InvalidDifficulty(number));
+    }
+
+    // the gas limit target doubled at London, so the bound applies to the doubled limit
+    let parent_gas_limit = if fork >= Fork::London && Fork::at(parent.number) < Fork::London {
+        parent.gas_limit * U256::from(ELASTICITY_MULTIPLIER)
+    } else {
+        parent.gas_limit
+    };
+    let gas_limit_delta = if header.gas_limit > parent_gas_limit {
+        header.gas_limit - parent_gas_limit
+    } else {
+        parent_gas_limit - header.gas_limit
+    };
+    if gas_limit_delta >= parent_gas_limit / U256::from(GAS_LIMIT_BOUND_DIVISOR)
+        || header.gas_limit < U256::from(MIN_GAS_LIMIT)
+    {
+        return Err(EraValidateError::InvalidGasLimit(number));
+    }
+    if header.gas_used > header.gas_limit {
+        return Err(EraValidateError::InvalidGasUsed(number));
+    }
+
+    let expected = (fork >= Fork::London).then(|| expected_base_fee(parent));
+    if header.base_fee_per_gas != expected {
+        log::error!(
+            "the base fee of block {} should be {:?} but is {:?}",
+            number,
+            expected,
+            header.base_fee_per_gas
+        );
+        return Err(EraValidateError::InvalidBaseFee(number));
+    }
+
+    Ok(())
+}
+
+/// Checks the consensus rules of consecutive headers, see [`check_header_rules`]. Headers
+/// must include their full header.
+#[derive(Clone, Debug, Default)]
+pub struct HeaderRuleValidator {
+    parent: Option<Header>,
+}
+
+impl HeaderRuleValidator {
+    pub fn new() -> Self {
+        HeaderRuleValidator::default()
+    }
+
+    /// Continues from an already validated header, e.g. the last header of the previous epoch
+    pub fn with_parent(parent: Header) -> Self {
+        HeaderRuleValidator {
+            parent: Some(parent),
+        }
+    }
+
+    /// Adds the next header. A header breaking a rule is rejected and the validator is left
+    /// as it was.
+    pub fn push(&mut self, header: &ExtHeaderRecord) -> Result<(), EraValidateError> {
+        let full_header = header
+            .full_header
+            .as_ref()
+            .ok_or(EraValidateError::ExtHeaderRecordError)?;
+
+        if let Some(parent) = &self.parent {
+            check_header_rules(parent, full_header)?;
+        }
+
+        self.parent = Some(full_header.clone());
+        Ok(())
+    }
+}
+
+/// Checks the consensus rules of `headers`, starting from `parent` if set.
+pub fn validate_header_rules(
+    headers: &[ExtHeaderRecord],
+    parent: Option<Header>,
+) -> Result<(), EraValidateError> {
+    let mut validator = match parent {
+        Some(parent) => HeaderRuleValidator::with_parent(parent),
+        None => HeaderRuleValidator::new(),
+    };
+    headers.iter().try_for_each(|header| validator.push(header))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epoch::MERGE_BLOCK;
+
+    #[test]
+    fn test_fork_at() {
+        assert_eq!(Fork::at(0), Fork::Frontier);
+        assert_eq!(Fork::at(1_150_000), Fork::Homestead);
+        assert_eq!(Fork::at(12_964_999), Fork::MuirGlacier);
+        assert_eq!(Fork::at(12_965_000), Fork::London);
+        assert_eq!(Fork::at(MERGE_BLOCK - 1), Fork::GrayGlacier);
+        assert_eq!(Fork::at(MERGE_BLOCK), Fork::Paris);
+    }
+
+    #[test]
+    fn test_frontier_difficulty() {
+        // mainnet blocks 1 and 2
+        let genesis = U256::from(17179869184u64);
+        let block_1 = difficulty(Fork::Frontier, genesis, false, 1438269988, 1);
+        assert_eq!(block_1, U256::from(17171480576u64));
+        let block_2 = difficulty(Fork::Frontier, block_1, false, 29, 2);
+        assert_eq!(block_2, U256::from(17163096064u64));
+
+        // the difficulty increases for blocks mined within 13 seconds
+        let parent = U256::from(1_000_000_000u64);
+        assert_eq!(
+            difficulty(Fork::Frontier, parent, false, 12, 3),
+            parent + parent / U256::from(2048)
+        );
+    }
+
+    #[test]
+    fn test_difficulty_bomb() {
+        let parent = U256::from(1_000_000_000_000u64);
+        let adjusted = parent + parent / U256::from(2048);
+
+        // the bomb adds 2^(period - 2) from period 2
+        assert_eq!(
+            difficulty(Fork::Homestead, parent, false, 5, 1_150_000),
+            adjusted + U256::from(1 << 9)
+        );
+        // delayed by 3 million blocks at Byzantium
+        assert_eq!(
+            difficulty(Fork::Byzantium, parent, false, 5, 4_370_000),
+            adjusted + U256::from(1 << 11)
+        );
+        assert_eq!(
+            difficulty(Fork::Byzantium, parent, false, 5, 3_100_000),
+            adjusted
+        );
+        // uncles speed the adjustment up since Byzantium
+        assert_eq!(
+            difficulty(Fork::Byzantium, parent, true, 9, 3_100_000),
+            adjusted
+        );
+        assert_eq!(
+            difficulty(Fork::Paris, parent, false, 12, MERGE_BLOCK),
+            U256::ZERO
+        );
+    }
+
+    #[test]
+    fn test_base_fee() {
+        let base_fee_per_gas = U256::from(1_000_000_000u64);
+        let gas_limit = U256::from(30_000_000u64);
+
+        assert_eq!(
+            base_fee(gas_limit, U256::from(15_000_000u64), base_fee_per_gas),
+            base_fee_per_gas
+        );
+        assert_eq!(
+            base_fee(gas_limit, gas_limit, base_fee_per_gas),
+            U256::from(1_125_000_000u64)
+        );
+        assert_eq!(
+            base_fee(gas_limit, U256::ZERO, base_fee_per_gas),
+            U256::from(875_000_000u64)
+        );
+        // the base fee always increases when the target is exceeded
+        assert_eq!(
+            base_fee(gas_limit, U256::from(15_000_001u64), U256::from(7)),
+            U256::from(8)
+        );
+    }
+}
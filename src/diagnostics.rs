@@ -0,0 +1,135 @@
+use ethportal_api::types::execution::accumulator::{EpochAccumulator, HeaderRecord};
+use ssz::Decode;
+
+use crate::{epoch::epoch_block_range, errors::EraValidateError, types::ExtHeaderRecord};
+
+/// The first header of an epoch whose record differs from a reference epoch accumulator
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeaderMismatch {
+    pub block_number: u64,
+    /// The record of the reference, `None` if the reference holds fewer records
+    pub expected: Option<HeaderRecord>,
+    /// The record of the header, `None` if there are fewer headers than reference records
+    pub actual: Option<HeaderRecord>,
+}
+
+impl HeaderMismatch {
+    /// Returns `true` if both records exist and their block hashes differ
+    pub fn hash_differs(&self) -> bool {
+        match (&self.expected, &self.actual) {
+            (Some(expected), Some(actual)) => expected.block_hash != actual.block_hash,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if both records exist and their total difficulties differ
+    pub fn total_difficulty_differs(&self) -> bool {
+        match (&self.expected, &self.actual) {
+            (Some(expected), Some(actual)) => expected.total_difficulty != actual.total_difficulty,
+            _ => false,
+        }
+    }
+}
+
+/// Decodes the records of an SSZ encoded epoch accumulator, such as the epoch accumulators
+/// distributed on the Portal network.
+pub fn decode_epoch_accumulator(bytes: &[u8]) -> Result<Vec<HeaderRecord>, EraValidateError> {
+    let epoch_accumulator = EpochAccumulator::from_ssz_bytes(bytes).map_err(|e| {
+        log::error!("error decoding epoch accumulator: {:?}", e);
+        EraValidateError::InvalidEpochAccumulatorFile
+    })?;
+    Ok(epoch_accumulator.to_vec())
+}
+
+/// Compares the headers of `epoch` with the records of a reference epoch accumulator,
+/// returning the first header whose block hash or total difficulty differs.
+///
+/// Returns `None` if every header matches its reference record.
+pub fn find_first_mismatch(
+    headers: &[ExtHeaderRecord],
+    epoch: usize,
+    reference: &[HeaderRecord],
+) -> Option<HeaderMismatch> {
+    let first_block = *epoch_block_range(epoch).start();
+    let len = headers.len().max(reference.len());
+
+    (0..len).find_map(|index| {
+        let expected = reference.get(index).cloned();
+        let actual = headers.get(index).map(HeaderRecord::from);
+        if expected == actual {
+            return None;
+        }
+        Some(HeaderMismatch {
+            block_number: headers
+                .get(index)
+                .map(|header| header.block_number)
+                .unwrap_or(first_block + index as u64),
+            expected,
+            actual,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epoch::MAX_EPOCH_SIZE;
+    use alloy_primitives::{B256, U256};
+
+    fn header(block_number: u64, total_difficulty: u64) -> ExtHeaderRecord {
+        ExtHeaderRecord {
+            block_hash: B256::with_last_byte(block_number as u8),
+            total_difficulty: U256::from(total_difficulty),
+            block_number,
+            full_header: None,
+        }
+    }
+
+    fn encode(records: &[HeaderRecord]) -> Vec<u8> {
+        records
+            .iter()
+            .flat_map(|record| {
+                let mut bytes = record.block_hash.to_vec();
+                bytes.extend_from_slice(&record.total_difficulty.to_le_bytes::<32>());
+                bytes
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_decode_epoch_accumulator() {
+        let records: Vec<HeaderRecord> = (0..4)
+            .map(|n| HeaderRecord::from(&header(n, n * 10)))
+            .collect();
+
+        assert_eq!(
+            decode_epoch_accumulator(&encode(&records)).unwrap(),
+            records
+        );
+        assert!(decode_epoch_accumulator(&[0; 65]).is_err());
+        assert!(decode_epoch_accumulator(&vec![0; (MAX_EPOCH_SIZE + 1) * 64]).is_err());
+    }
+
+    #[test]
+    fn test_find_first_mismatch() {
+        let headers: Vec<ExtHeaderRecord> = (8192..8196).map(|n| header(n, n)).collect();
+        let mut reference: Vec<HeaderRecord> = headers.iter().map(HeaderRecord::from).collect();
+
+        assert_eq!(find_first_mismatch(&headers, 1, &reference), None);
+
+        reference[2].total_difficulty += U256::from(1);
+        reference[3].block_hash = B256::ZERO;
+        let mismatch = find_first_mismatch(&headers, 1, &reference).unwrap();
+        assert_eq!(mismatch.block_number, 8194);
+        assert!(mismatch.total_difficulty_differs());
+        assert!(!mismatch.hash_differs());
+
+        let mismatch = find_first_mismatch(&headers, 1, &reference[..2]).unwrap();
+        assert_eq!(mismatch.block_number, 8194);
+        assert_eq!(mismatch.expected, None);
+
+        let mismatch = find_first_mismatch(&headers[..1], 1, &reference).unwrap();
+        assert_eq!(mismatch.block_number, 8193);
+        assert_eq!(mismatch.actual, None);
+    }
+}
@@ -1,3 +1,5 @@
+use std::{cmp::Ordering, ops::RangeInclusive};
+
 /// The maximum number of slots per epoch in Ethereum.
 /// In the context of Proof of Stake (PoS) consensus, an epoch is a collection of slots
 /// during which validators propose and attest to blocks. The maximum size of an epoch
@@ -13,3 +15,146 @@ pub const FINAL_EPOCH: usize = 1896;
 /// "The Merge" took place at block 15537394, when the Ethereum network fully switched
 /// from Proof of Work (PoW) to Proof of Stake (PoS).
 pub const MERGE_BLOCK: u64 = 15537394;
+
+/// Returns the epoch that contains `block_number`.
+pub fn epoch_of_block(block_number: u64) -> usize {
+    (block_number / MAX_EPOCH_SIZE as u64) as usize
+}
+
+/// Returns the inclusive range of block numbers that make up `epoch`.
+pub fn epoch_block_range(epoch: usize) -> RangeInclusive<u64> {
+    let start = epoch as u64 * MAX_EPOCH_SIZE as u64;
+    start..=start + MAX_EPOCH_SIZE as u64 - 1
+}
+
+/// Returns the number of pre-merge headers in `epoch`, i.e. the number of records of its epoch
+/// accumulator. [`FINAL_EPOCH`] ends with the block before [`MERGE_BLOCK`], and later epochs
+/// hold no pre-merge header.
+pub fn pre_merge_epoch_size(epoch: usize) -> usize {
+    match epoch.cmp(&FINAL_EPOCH) {
+        Ordering::Less => MAX_EPOCH_SIZE,
+        Ordering::Equal => offset_in_epoch(MERGE_BLOCK),
+        Ordering::Greater => 0,
+    }
+}
+
+/// Returns the inclusive range of epochs needed to cover every block between
+/// `start_block` and `end_block`, both inclusive.
+///
+/// The returned range is empty if `start_block` is greater than `end_block`.
+pub fn epochs_covering(start_block: u64, end_block: u64) -> RangeInclusive<usize> {
+    if start_block > end_block {
+        // blocks of the same epoch would otherwise give that epoch
+        return RangeInclusive::new(1, 0);
+    }
+    epoch_of_block(start_block)..=epoch_of_block(end_block)
+}
+
+/// Returns the position of `block_number` within its epoch, i.e. its index in the
+/// epoch accumulator.
+pub fn offset_in_epoch(block_number: u64) -> usize {
+    (block_number % MAX_EPOCH_SIZE as u64) as usize
+}
+
+/// Returns true if `block_number` was produced before [`MERGE_BLOCK`], and so is part of
+/// the pre-merge header accumulator.
+pub fn is_pre_merge(block_number: u64) -> bool {
+    block_number < MERGE_BLOCK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_epoch_of_block() {
+        assert_eq!(epoch_of_block(0), 0);
+        assert_eq!(epoch_of_block(8191), 0);
+        assert_eq!(epoch_of_block(8192), 1);
+        assert_eq!(epoch_of_block(8193), 1);
+        assert_eq!(epoch_of_block(16383), 1);
+        assert_eq!(epoch_of_block(16384), 2);
+
+        // above 2^24 an f32 can no longer represent every block number
+        assert_eq!(epoch_of_block(16_785_407), 2048);
+        assert_eq!(epoch_of_block(16_785_408), 2049);
+
+        assert_eq!(epoch_of_block(MERGE_BLOCK - 1), FINAL_EPOCH);
+        assert_eq!(epoch_of_block(MERGE_BLOCK), FINAL_EPOCH);
+        assert_eq!(
+            epoch_of_block(*epoch_block_range(FINAL_EPOCH).end()),
+            FINAL_EPOCH
+        );
+        assert_eq!(
+            epoch_of_block(*epoch_block_range(FINAL_EPOCH).end() + 1),
+            FINAL_EPOCH + 1
+        );
+    }
+
+    #[test]
+    fn test_epoch_block_range() {
+        assert_eq!(epoch_block_range(0), 0..=8191);
+        assert_eq!(epoch_block_range(1), 8192..=16383);
+        assert_eq!(epoch_block_range(2048), 16_777_216..=16_785_407);
+        assert!(epoch_block_range(FINAL_EPOCH).contains(&MERGE_BLOCK));
+
+        for epoch in [0, 1, 2, 1000, FINAL_EPOCH, FINAL_EPOCH + 1] {
+            let range = epoch_block_range(epoch);
+            assert_eq!(range.clone().count(), MAX_EPOCH_SIZE);
+            assert_eq!(epoch_of_block(*range.start()), epoch);
+            assert_eq!(epoch_of_block(*range.end()), epoch);
+            assert_eq!(offset_in_epoch(*range.start()), 0);
+            assert_eq!(offset_in_epoch(*range.end()), MAX_EPOCH_SIZE - 1);
+        }
+    }
+
+    #[test]
+    fn test_epochs_covering() {
+        assert_eq!(epochs_covering(0, 0), 0..=0);
+        assert_eq!(epochs_covering(301, 402), 0..=0);
+        assert_eq!(epochs_covering(0, 8191), 0..=0);
+        // the last block of an epoch must not pull in the next one
+        assert_eq!(epochs_covering(8191, 8191), 0..=0);
+        // the first block of an epoch must be covered
+        assert_eq!(epochs_covering(8191, 8192), 0..=1);
+        assert_eq!(epochs_covering(8192, 8192), 1..=1);
+        assert_eq!(epochs_covering(12_000_000, 12_000_000), 1464..=1464);
+        assert_eq!(epochs_covering(16_777_215, 16_785_407), 2047..=2048);
+        assert_eq!(
+            epochs_covering(MERGE_BLOCK - 1, MERGE_BLOCK),
+            FINAL_EPOCH..=FINAL_EPOCH
+        );
+        assert!(epochs_covering(8192, 8191).is_empty());
+        assert!(epochs_covering(8191, 8190).is_empty());
+        assert!(epochs_covering(16_385, 0).is_empty());
+    }
+
+    #[test]
+    fn test_pre_merge_epoch_size() {
+        assert_eq!(pre_merge_epoch_size(0), MAX_EPOCH_SIZE);
+        assert_eq!(pre_merge_epoch_size(FINAL_EPOCH - 1), MAX_EPOCH_SIZE);
+        assert_eq!(pre_merge_epoch_size(FINAL_EPOCH), 5362);
+        assert_eq!(
+            *epoch_block_range(FINAL_EPOCH).start() + pre_merge_epoch_size(FINAL_EPOCH) as u64,
+            MERGE_BLOCK
+        );
+        assert_eq!(pre_merge_epoch_size(FINAL_EPOCH + 1), 0);
+    }
+
+    #[test]
+    fn test_offset_in_epoch() {
+        assert_eq!(offset_in_epoch(0), 0);
+        assert_eq!(offset_in_epoch(8191), 8191);
+        assert_eq!(offset_in_epoch(8192), 0);
+        assert_eq!(offset_in_epoch(12_000_000), 12_000_000 - 1464 * 8192);
+        assert_eq!(offset_in_epoch(MERGE_BLOCK), 5362);
+    }
+
+    #[test]
+    fn test_is_pre_merge() {
+        assert!(is_pre_merge(0));
+        assert!(is_pre_merge(MERGE_BLOCK - 1));
+        assert!(!is_pre_merge(MERGE_BLOCK));
+        assert!(!is_pre_merge(MERGE_BLOCK + 1));
+    }
+}
@@ -1,42 +1,119 @@
-use std::path::Path;
+use std::{path::PathBuf, sync::Arc};
 
+use alloy_primitives::B256;
 use ethportal_api::types::execution::accumulator::{EpochAccumulator, HeaderRecord};
+use rayon::prelude::*;
 use tree_hash::TreeHash;
 use trin_validation::accumulator::PreMergeAccumulator;
 
 use crate::{
-    epoch::{FINAL_EPOCH, MAX_EPOCH_SIZE, MERGE_BLOCK},
+    epoch::{
+        epoch_block_range, epoch_of_block, is_pre_merge, offset_in_epoch, pre_merge_epoch_size,
+        FINAL_EPOCH, MAX_EPOCH_SIZE,
+    },
     errors::{EraValidateError, HeaderAccumulatorError},
-    sync::{Lock, LockEntry},
-    types::ExtHeaderRecord,
+    sync::{EpochRecord, JsonSyncStore, Provenance, SyncStore},
+    types::{pre_merge_accumulator_id, ExtHeaderRecord},
 };
 
+/// Options for [`EraValidator::era_validate`]
+#[derive(Clone, Debug, Default)]
+pub struct EraValidateConfig {
+    sync_store: Option<Arc<dyn SyncStore>>,
+    sources: Vec<String>,
+    workers: Option<usize>,
+    verify_header_hashes: bool,
+}
+
+impl EraValidateConfig {
+    pub fn new() -> Self {
+        EraValidateConfig::default()
+    }
+
+    /// Keeps a record of validated epochs in the lockfile at `path`, skipping the
+    /// epochs it already holds. Datasets validated with different lockfiles keep
+    /// separate sync state.
+    pub fn with_lockfile(self, path: impl Into<PathBuf>) -> Self {
+        self.with_sync_store(JsonSyncStore::new(path))
+    }
+
+    /// Keeps a record of validated epochs in `sync_store`, skipping the epochs it already holds.
+    pub fn with_sync_store(mut self, sync_store: impl SyncStore + 'static) -> Self {
+        self.sync_store = Some(Arc::new(sync_store));
+        self
+    }
+
+    pub fn sync_store(&self) -> Option<&dyn SyncStore> {
+        self.sync_store.as_deref()
+    }
+
+    /// Records `sources`, e.g. the flat files the headers were read from, in the
+    /// provenance of every epoch stored in the sync store.
+    pub fn with_sources(mut self, sources: Vec<String>) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    pub fn sources(&self) -> &[String] {
+        &self.sources
+    }
+
+    /// Validates up to `workers` epochs concurrently, on a thread pool of `workers` threads.
+    /// Epochs are validated one at a time unless this is set.
+    ///
+    /// The sync store is still updated by a single thread, in epoch order.
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = Some(workers.max(1));
+        self
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers.unwrap_or(1)
+    }
+
+    /// Checks that the block hash of every header is the hash of its full header before
+    /// validating its epoch, see [`ExtHeaderRecord::verify_hash`]. The headers must then
+    /// include their full header.
+    pub fn with_header_hash_check(mut self, verify_header_hashes: bool) -> Self {
+        self.verify_header_hashes = verify_header_hashes;
+        self
+    }
+
+    pub fn verify_header_hashes(&self) -> bool {
+        self.verify_header_hashes
+    }
+}
+
 pub trait EraValidator {
     type Error;
 
     /// Validates many headers against a header accumulator
     ///
-    /// It also keeps a record in `lockfile.json` of the validated epochs to skip them
+    /// If `config` sets a lockfile, it also keeps a record there of the validated epochs to skip them
     ///
     /// # Arguments
     ///
-    /// * `headers`-  A mutable vector of [`ExtHeaderRecord`]. The Vector can be any size,
-    ///   however, it must be in chunks of 8192 blocks to work properly to function without error
+    /// * `headers`-  A mutable vector of [`ExtHeaderRecord`]. It must hold exactly the pre-merge
+    ///   blocks of the epochs from `start_epoch` up to `end_epoch` excluded, in order, otherwise
+    ///   an error naming the first unexpected block number or the expected number of headers is
+    ///   returned
     /// * `start_epoch` -  The epoch number that all the first 8192 blocks are set located
     /// * `end_epoch` -  The epoch number that all the last 8192 blocks are located
-    /// * `use_lock` - when set to true, uses the lockfile to store already processed blocks. True by default
+    /// * `config` - validation options, see [`EraValidateConfig`]
     fn era_validate(
         &self,
         headers: Vec<ExtHeaderRecord>,
         start_epoch: usize,
         end_epoch: Option<usize>,
-        use_lock: bool,
+        config: &EraValidateConfig,
     ) -> Result<Vec<usize>, Self::Error>;
 
     /// takes 8192 block headers and checks if they consist in a valid epoch.
     ///
     /// An epoch must respect the order of blocks, i.e., block numbers for epoch
-    /// 0 must start from block 0 to block 8191.
+    /// 0 must start from block 0 to block 8191. [`FINAL_EPOCH`] only holds the headers
+    /// preceding [`MERGE_BLOCK`](crate::epoch::MERGE_BLOCK), see
+    /// [`pre_merge_epoch_size`], and its post-merge headers are ignored.
     ///
     /// headers can only be validated for now against epochs before The Merge.
     /// All pre-merge blocks (which are numbered before [`FINAL_EPOCH`]), are validated using
@@ -56,63 +133,21 @@ impl EraValidator for PreMergeAccumulator {
 
     fn era_validate(
         &self,
-        mut headers: Vec<ExtHeaderRecord>,
+        headers: Vec<ExtHeaderRecord>,
         start_epoch: usize,
         end_epoch: Option<usize>,
-        use_lock: bool,
+        config: &EraValidateConfig,
     ) -> Result<Vec<usize>, Self::Error> {
-        let end_epoch = end_epoch.unwrap_or(start_epoch + 1);
-
-        // Ensure start epoch is less than end epoch
-        if start_epoch >= end_epoch {
-            Err(EraValidateError::EndEpochLessThanStartEpoch)?;
-        }
-
-        let mut validated_epochs = Vec::new();
-        for epoch in start_epoch..end_epoch {
-            // checks if epoch was already synced form lockfile.
-            if use_lock {
-                let file_path = Path::new("./lockfile.json");
-                let lock_file = Lock::from_file(file_path)?;
-
-                match lock_file.check_sync_state(file_path, epoch, self.historical_epochs[epoch].0)
-                {
-                    Ok(true) => {
-                        log::info!("Skipping, epoch already synced: {}", epoch);
-                        continue;
-                    }
-                    Ok(false) => {
-                        log::info!("syncing new epoch: {}", epoch);
-                    }
-                    Err(e) => {
-                        return {
-                            log::error!("error: {}", e);
-                            Err(EraValidateError::EpochAccumulatorError.into())
-                        }
-                    }
-                }
-            }
-            let epoch_headers: Vec<ExtHeaderRecord> = headers.drain(0..MAX_EPOCH_SIZE).collect();
-            let root = self.process_headers(epoch_headers, epoch)?;
-            validated_epochs.push(epoch);
-
-            // stores the validated epoch into lockfile to avoid validating again and keeping a concise state
-            if use_lock {
-                let path = Path::new("./lockfile.json");
-                let mut lock_file = Lock::from_file(path)?;
-                lock_file.update(LockEntry::new(&epoch, root));
-
-                match lock_file.store_last_state(path) {
-                    Ok(_) => {}
-                    Err(e) => {
-                        log::error!("error: {}", e);
-                        return Err(EraValidateError::EpochAccumulatorError.into());
-                    }
-                }
-            }
-        }
-
-        Ok(validated_epochs)
+        // the identifier is only recorded in the provenance of stored epochs
+        let accumulator_id = config.sync_store().map(|_| pre_merge_accumulator_id(self));
+        validate_epochs(
+            self,
+            headers,
+            start_epoch,
+            end_epoch,
+            config,
+            accumulator_id,
+        )
     }
 
     fn process_headers(
@@ -120,20 +155,24 @@ impl EraValidator for PreMergeAccumulator {
         mut headers: Vec<ExtHeaderRecord>,
         epoch: usize,
     ) -> Result<[u8; 32], Self::Error> {
-        if headers.len() != MAX_EPOCH_SIZE {
-            Err(EraValidateError::InvalidEpochLength)?;
-        }
-
-        if headers[0].block_number % MAX_EPOCH_SIZE as u64 != 0 {
-            Err(EraValidateError::InvalidEpochStart)?;
+        if epoch > FINAL_EPOCH {
+            Err(EraValidateError::EpochAfterMerge(epoch))?;
         }
 
-        if epoch > FINAL_EPOCH {
+        if epoch == FINAL_EPOCH && headers.len() > pre_merge_epoch_size(epoch) {
             log::warn!(
-                "the blocks from this epoch are not being validated since they are post merge.
+                "the blocks from this epoch after the merge are not being validated.
             For post merge blocks, use the sync-committee subprotocol"
             );
-            headers.retain(|header: &ExtHeaderRecord| header.block_number < MERGE_BLOCK);
+            headers.retain(|header: &ExtHeaderRecord| is_pre_merge(header.block_number));
+        }
+
+        if headers.len() != pre_merge_epoch_size(epoch) {
+            Err(EraValidateError::InvalidEpochLength)?;
+        }
+
+        if offset_in_epoch(headers[0].block_number) != 0 {
+            Err(EraValidateError::InvalidEpochStart)?;
         }
 
         let header_records: Vec<_> = headers.into_iter().map(HeaderRecord::from).collect();
@@ -154,3 +193,286 @@ impl EraValidator for PreMergeAccumulator {
         Ok(root)
     }
 }
+
+/// Implements [`EraValidator::era_validate`], given the identifier of `pre_merge_accumulator`
+/// if the epochs are stored in a sync store, so callers validating many times compute it once.
+fn validate_epochs(
+    pre_merge_accumulator: &PreMergeAccumulator,
+    mut headers: Vec<ExtHeaderRecord>,
+    start_epoch: usize,
+    end_epoch: Option<usize>,
+    config: &EraValidateConfig,
+    accumulator_id: Option<B256>,
+) -> Result<Vec<usize>, HeaderAccumulatorError> {
+    let end_epoch = end_epoch.unwrap_or(start_epoch + 1);
+
+    // Ensure start epoch is less than end epoch
+    if start_epoch >= end_epoch {
+        Err(EraValidateError::EndEpochLessThanStartEpoch)?;
+    }
+
+    if end_epoch - 1 > FINAL_EPOCH {
+        Err(EraValidateError::EpochAfterMerge(end_epoch - 1))?;
+    }
+
+    // the headers must be exactly the blocks of the requested epochs, in order
+    let first_block = *epoch_block_range(start_epoch).start();
+    for (expected, header) in (first_block..).zip(&headers) {
+        if header.block_number != expected {
+            Err(EraValidateError::UnexpectedBlockNumber(
+                expected,
+                header.block_number,
+            ))?;
+        }
+    }
+    let expected_len: usize = (start_epoch..end_epoch).map(pre_merge_epoch_size).sum();
+    if headers.len() != expected_len {
+        Err(EraValidateError::UnexpectedHeaderCount(
+            expected_len,
+            headers.len(),
+        ))?;
+    }
+
+    // split the headers before skipping any epoch, so every epoch gets its own headers
+    let mut pending: Vec<(usize, Vec<ExtHeaderRecord>)> = Vec::new();
+    for epoch in start_epoch..end_epoch {
+        let epoch_headers: Vec<ExtHeaderRecord> =
+            headers.drain(0..pre_merge_epoch_size(epoch)).collect();
+
+        // checks if epoch was already synced form the sync store.
+        if let Some(sync_store) = config.sync_store() {
+            match sync_store
+                .check_sync_state(epoch, pre_merge_accumulator.historical_epochs[epoch].0)
+            {
+                Ok(true) => {
+                    log::info!("Skipping, epoch already synced: {}", epoch);
+                    continue;
+                }
+                Ok(false) => {
+                    log::info!("syncing new epoch: {}", epoch);
+                }
+                Err(e) => {
+                    log::error!("error: {}", e);
+                    return Err(e);
+                }
+            }
+        }
+        pending.push((epoch, epoch_headers));
+    }
+
+    // a single epoch is validated on the calling thread, which spares streams a pool per epoch
+    let pool = if config.workers() > 1 && pending.len() > 1 {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(config.workers())
+            .build()
+            .map_err(|e| {
+                log::error!("error: {}", e);
+                EraValidateError::WorkerPoolError
+            })?;
+        Some(pool)
+    } else {
+        None
+    };
+
+    let validate = |(epoch, epoch_headers): (usize, Vec<ExtHeaderRecord>)| {
+        let provenance = accumulator_id.map(|accumulator_id| {
+            Provenance::new(accumulator_id, config.sources().to_vec(), &epoch_headers)
+        });
+        let hashes = if config.verify_header_hashes() {
+            epoch_headers
+                .iter()
+                .try_for_each(ExtHeaderRecord::verify_hash)
+        } else {
+            Ok(())
+        };
+        let root = hashes
+            .map_err(HeaderAccumulatorError::from)
+            .and_then(|_| pre_merge_accumulator.process_headers(epoch_headers, epoch));
+        (epoch, root, provenance)
+    };
+
+    let mut validated_epochs = Vec::new();
+    let mut pending = pending.into_iter();
+    loop {
+        // validate a batch of epochs, then store the results in order so that the sync
+        // store keeps up with the validation
+        let batch: Vec<_> = pending.by_ref().take(config.workers()).collect();
+        if batch.is_empty() {
+            break;
+        }
+        let results: Vec<_> = match &pool {
+            Some(pool) => pool.install(|| batch.into_par_iter().map(validate).collect()),
+            None => batch.into_iter().map(validate).collect(),
+        };
+
+        for (epoch, root, provenance) in results {
+            let root = root?;
+            validated_epochs.push(epoch);
+
+            // stores the validated epoch into the sync store to avoid validating again and keeping a concise state
+            if let (Some(sync_store), Some(provenance)) = (config.sync_store(), provenance) {
+                if let Err(e) =
+                    sync_store.put(EpochRecord::new(epoch, root).with_provenance(provenance))
+                {
+                    log::error!("error: {}", e);
+                    return Err(HeaderAccumulatorError::SyncError(e));
+                }
+            }
+        }
+    }
+
+    Ok(validated_epochs)
+}
+
+/// Whether an epoch completed by a [`StreamingEraValidator`] was validated
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EpochStatus {
+    Validated,
+    /// The sync store already recorded the epoch, so it was skipped
+    AlreadySynced,
+}
+
+/// The outcome of validating one epoch of a stream
+#[derive(Debug)]
+pub struct EpochResult {
+    pub epoch: usize,
+    pub result: Result<EpochStatus, HeaderAccumulatorError>,
+}
+
+/// Validates headers as they arrive, buffering at most one epoch.
+///
+/// Headers must be pushed in order, starting at the first block of an epoch. Every time an
+/// epoch is complete it is validated like with [`EraValidator::era_validate`], so the sync store
+/// of the config is used as usual.
+pub struct StreamingEraValidator<'a> {
+    pre_merge_accumulator: &'a PreMergeAccumulator,
+    accumulator_id: Option<B256>,
+    config: EraValidateConfig,
+    headers: Vec<ExtHeaderRecord>,
+}
+
+impl<'a> StreamingEraValidator<'a> {
+    pub fn new(pre_merge_accumulator: &'a PreMergeAccumulator, config: EraValidateConfig) -> Self {
+        StreamingEraValidator {
+            pre_merge_accumulator,
+            accumulator_id: config
+                .sync_store()
+                .map(|_| pre_merge_accumulator_id(pre_merge_accumulator)),
+            config,
+            headers: Vec::with_capacity(MAX_EPOCH_SIZE),
+        }
+    }
+
+    /// Number of headers of the current epoch received so far
+    pub fn buffered(&self) -> usize {
+        self.headers.len()
+    }
+
+    /// Adds the next header of the stream, returning the result of its epoch if the header
+    /// completes it.
+    ///
+    /// A header that does not follow the previous one, does not start an epoch, or is after the
+    /// merge is rejected and the stream is left as it was. [`FINAL_EPOCH`] is complete with
+    /// the block preceding the merge.
+    pub fn push(
+        &mut self,
+        header: ExtHeaderRecord,
+    ) -> Result<Option<EpochResult>, EraValidateError> {
+        if !is_pre_merge(header.block_number) {
+            return Err(EraValidateError::BlockAfterMerge(header.block_number));
+        }
+
+        match self.headers.last() {
+            Some(last) if header.block_number != last.block_number + 1 => {
+                return Err(EraValidateError::UnexpectedBlockNumber(
+                    last.block_number + 1,
+                    header.block_number,
+                ));
+            }
+            Some(_) => {}
+            None => {
+                if offset_in_epoch(header.block_number) != 0 {
+                    return Err(EraValidateError::InvalidEpochStart);
+                }
+            }
+        }
+
+        let epoch = epoch_of_block(header.block_number);
+        self.headers.push(header);
+        if self.headers.len() < pre_merge_epoch_size(epoch) {
+            return Ok(None);
+        }
+
+        let headers = std::mem::replace(&mut self.headers, Vec::with_capacity(MAX_EPOCH_SIZE));
+        let result = validate_epochs(
+            self.pre_merge_accumulator,
+            headers,
+            epoch,
+            None,
+            &self.config,
+            self.accumulator_id,
+        )
+        .map(|validated| {
+            if validated.is_empty() {
+                EpochStatus::AlreadySynced
+            } else {
+                EpochStatus::Validated
+            }
+        });
+
+        Ok(Some(EpochResult { epoch, result }))
+    }
+
+    /// Ends the stream, returning an error if it stopped in the middle of an epoch.
+    pub fn finish(self) -> Result<(), EraValidateError> {
+        match self.headers.first() {
+            Some(first) => Err(EraValidateError::PartialEpoch(
+                epoch_of_block(first.block_number),
+                self.headers.len(),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Validates every epoch of `headers`, yielding one result per completed epoch.
+    ///
+    /// The iterator ends after the first error of the stream itself, including a trailing
+    /// partial epoch.
+    pub fn validate_iter<I>(self, headers: I) -> StreamingEpochs<'a, I::IntoIter>
+    where
+        I: IntoIterator<Item = ExtHeaderRecord>,
+    {
+        StreamingEpochs {
+            validator: Some(self),
+            headers: headers.into_iter(),
+        }
+    }
+}
+
+/// Iterator over the epochs validated by a [`StreamingEraValidator`], see
+/// [`StreamingEraValidator::validate_iter`]
+pub struct StreamingEpochs<'a, I> {
+    validator: Option<StreamingEraValidator<'a>>,
+    headers: I,
+}
+
+impl<I: Iterator<Item = ExtHeaderRecord>> Iterator for StreamingEpochs<'_, I> {
+    type Item = Result<EpochResult, EraValidateError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let validator = self.validator.as_mut()?;
+
+        for header in self.headers.by_ref() {
+            match validator.push(header) {
+                Ok(None) => continue,
+                Ok(Some(result)) => return Some(Ok(result)),
+                Err(e) => {
+                    self.validator = None;
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        self.validator.take()?.finish().err().map(Err)
+    }
+}
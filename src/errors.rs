@@ -2,6 +2,11 @@ use std::fmt;
 
 use sf_protos::StreamingFastProtosError;
 
+use crate::{
+    epoch::{pre_merge_epoch_size, FINAL_EPOCH, MERGE_BLOCK},
+    sync::RootConflict,
+};
+
 #[derive(Debug)]
 pub enum HeaderAccumulatorError {
     EraValidateError(EraValidateError),
@@ -51,12 +56,63 @@ pub enum EraValidateError {
     InvalidEpochLength,
     ExtHeaderRecordError,
     InvalidBlockRange(u64, u64),
+    BlockRangeNotCovered(u64, u64),
+    UnexpectedBlockNumber(u64, u64),
+    UnsupportedProofBundleVersion(u8),
+    WorkerPoolError,
+    PartialEpoch(usize, usize),
+    EpochAfterMerge(usize),
+    BlockAfterMerge(u64),
+    UnexpectedHeaderCount(usize, usize),
+    InvalidEpochAccumulatorFile,
+    BlockHashMismatch(u64),
+    ParentHashMismatch(u64),
+    DuplicateBlockNumber(u64),
+    MissingBlockNumbers(u64, u64),
+    TotalDifficultyMismatch(u64),
+    MissingSeal(u64),
+    MixHashMismatch(u64),
+    InsufficientProofOfWork(u64),
+    InvalidTimestamp(u64),
+    InvalidExtraData(u64),
+    InvalidDifficulty(u64),
+    InvalidGasLimit(u64),
+    InvalidGasUsed(u64),
+    InvalidBaseFee(u64),
+    TransactionsRootMismatch(u64),
+    ReceiptsRootMismatch(u64),
+    UnsupportedTransactionType(u64, i32),
+    MissingReceipt(u64),
+    UnclesHashMismatch(u64),
+    TooManyUncles(u64),
+    InvalidUncleDepth(u64, u64),
 }
 
 #[derive(Debug)]
 pub enum SyncError {
     LockfileIoError(std::io::Error),
     LockfileReadError,
+    LockfileJsonError(serde_json::Error),
+    /// The stored root of `epoch` is not valid base64
+    InvalidEncoding {
+        epoch: String,
+        value: String,
+    },
+    /// The stored root of `epoch` does not decode to a 32-byte hash
+    WrongLength {
+        epoch: String,
+        value: String,
+        length: usize,
+    },
+    /// The entry of `epoch` does not follow the sync state schema
+    SchemaMismatch {
+        epoch: String,
+        value: String,
+    },
+    /// Locks being merged record different roots for these epochs
+    ConflictingRoots(Vec<RootConflict>),
+    SqliteError(rusqlite::Error),
+    UnsupportedLockfileVersion(u32),
 }
 
 impl std::error::Error for EraValidateError {}
@@ -101,7 +157,12 @@ impl fmt::Display for EraValidateError {
                 write!(f, "Error decoding total difficulty")
             }
             InvalidEpochLength => {
-                write!(f, "blocks in epoch must be exactly 8192 units")
+                write!(
+                    f,
+                    "blocks in epoch must be exactly 8192 units, or the {} pre-merge ones of epoch {}",
+                    pre_merge_epoch_size(FINAL_EPOCH),
+                    FINAL_EPOCH
+                )
             }
             InvalidEpochStart => {
                 write!(
@@ -115,6 +176,178 @@ impl fmt::Display for EraValidateError {
             InvalidBlockRange(start, end) => {
                 write!(f, "Invalid block range: {} - {}", start, end)
             }
+            BlockRangeNotCovered(start, end) => {
+                write!(
+                    f,
+                    "Headers do not cover the epochs of block range: {} - {}",
+                    start, end
+                )
+            }
+            UnexpectedBlockNumber(expected, actual) => {
+                write!(
+                    f,
+                    "Unexpected block number: expected {}, found {}",
+                    expected, actual
+                )
+            }
+            UnsupportedProofBundleVersion(version) => {
+                write!(f, "Unsupported inclusion proof bundle version: {}", version)
+            }
+            WorkerPoolError => write!(f, "Error starting the validation worker threads"),
+            PartialEpoch(epoch, headers) => {
+                write!(
+                    f,
+                    "Epoch {} is incomplete: {} of {} headers",
+                    epoch,
+                    headers,
+                    pre_merge_epoch_size(epoch)
+                )
+            }
+            UnexpectedHeaderCount(expected, actual) => {
+                write!(
+                    f,
+                    "Unexpected number of headers: expected {}, found {}",
+                    expected, actual
+                )
+            }
+            InvalidEpochAccumulatorFile => write!(f, "Invalid epoch accumulator file"),
+            BlockHashMismatch(block_number) => {
+                write!(
+                    f,
+                    "Block hash of block {} does not match its header",
+                    block_number
+                )
+            }
+            ParentHashMismatch(block_number) => {
+                write!(
+                    f,
+                    "Parent hash of block {} does not match the previous block",
+                    block_number
+                )
+            }
+            DuplicateBlockNumber(block_number) => {
+                write!(f, "Duplicate block number: {}", block_number)
+            }
+            MissingBlockNumbers(start, end) => {
+                write!(f, "Missing block numbers: {} - {}", start, end)
+            }
+            TotalDifficultyMismatch(block_number) => {
+                write!(
+                    f,
+                    "Total difficulty of block {} does not match the sum of difficulties",
+                    block_number
+                )
+            }
+            MissingSeal(block_number) => {
+                write!(f, "Block {} is not sealed by proof of work", block_number)
+            }
+            MixHashMismatch(block_number) => {
+                write!(
+                    f,
+                    "Mix hash of block {} does not match its nonce",
+                    block_number
+                )
+            }
+            InsufficientProofOfWork(block_number) => {
+                write!(
+                    f,
+                    "Proof of work of block {} does not meet its difficulty",
+                    block_number
+                )
+            }
+            InvalidTimestamp(block_number) => {
+                write!(
+                    f,
+                    "Timestamp of block {} is not after the one of its parent",
+                    block_number
+                )
+            }
+            InvalidExtraData(block_number) => {
+                write!(f, "Extra data of block {} is invalid", block_number)
+            }
+            InvalidDifficulty(block_number) => {
+                write!(
+                    f,
+                    "Difficulty of block {} does not follow the difficulty adjustment",
+                    block_number
+                )
+            }
+            InvalidGasLimit(block_number) => {
+                write!(
+                    f,
+                    "Gas limit of block {} changed more than allowed from its parent",
+                    block_number
+                )
+            }
+            InvalidGasUsed(block_number) => {
+                write!(
+                    f,
+                    "Gas used by block {} exceeds its gas limit",
+                    block_number
+                )
+            }
+            InvalidBaseFee(block_number) => {
+                write!(
+                    f,
+                    "Base fee of block {} does not follow from its parent",
+                    block_number
+                )
+            }
+            TransactionsRootMismatch(block_number) => {
+                write!(
+                    f,
+                    "Transactions of block {} do not match its transactions root",
+                    block_number
+                )
+            }
+            ReceiptsRootMismatch(block_number) => {
+                write!(
+                    f,
+                    "Receipts of block {} do not match its receipts root",
+                    block_number
+                )
+            }
+            UnsupportedTransactionType(block_number, transaction_type) => {
+                write!(
+                    f,
+                    "Block {} contains a transaction of unsupported type {}",
+                    block_number, transaction_type
+                )
+            }
+            MissingReceipt(block_number) => {
+                write!(f, "A transaction of block {} has no receipt", block_number)
+            }
+            UnclesHashMismatch(block_number) => {
+                write!(
+                    f,
+                    "Uncles of block {} do not match its uncles hash",
+                    block_number
+                )
+            }
+            TooManyUncles(block_number) => {
+                write!(f, "Block {} has more than two uncles", block_number)
+            }
+            InvalidUncleDepth(block_number, uncle_number) => {
+                write!(
+                    f,
+                    "Block {} cannot include uncle {}, which is not among its six previous generations",
+                    block_number, uncle_number
+                )
+            }
+            EpochAfterMerge(epoch) => {
+                write!(
+                    f,
+                    "Epoch {} is after the merge, the last pre-merge epoch is {}",
+                    epoch, FINAL_EPOCH
+                )
+            }
+            BlockAfterMerge(block_number) => {
+                write!(
+                    f,
+                    "Block {} is after the merge, the first post-merge block is {}",
+                    block_number, MERGE_BLOCK
+                )
+            }
         }
     }
 }
@@ -124,6 +357,32 @@ impl fmt::Display for SyncError {
         match self {
             Self::LockfileIoError(e) => write!(f, "Error reading lockfile: {e}"),
             Self::LockfileReadError => write!(f, "Epoch not found"),
+            Self::LockfileJsonError(e) => write!(f, "Error encoding lockfile: {e}"),
+            Self::InvalidEncoding { epoch, value } => {
+                write!(f, "Invalid base64 root for epoch {epoch}: {value:?}")
+            }
+            Self::WrongLength {
+                epoch,
+                value,
+                length,
+            } => write!(
+                f,
+                "Root for epoch {epoch} is {length} bytes long instead of 32: {value:?}"
+            ),
+            Self::SchemaMismatch { epoch, value } => {
+                write!(f, "Invalid sync state entry for epoch {epoch:?}: {value:?}")
+            }
+            Self::ConflictingRoots(conflicts) => {
+                let epochs: Vec<String> = conflicts
+                    .iter()
+                    .map(|conflict| conflict.epoch.to_string())
+                    .collect();
+                write!(f, "Conflicting roots for epochs: {}", epochs.join(", "))
+            }
+            Self::SqliteError(e) => write!(f, "Error accessing sync database: {e}"),
+            Self::UnsupportedLockfileVersion(version) => {
+                write!(f, "Unsupported lockfile version: {version}")
+            }
         }
     }
 }
@@ -134,6 +393,18 @@ impl From<std::io::Error> for SyncError {
     }
 }
 
+impl From<serde_json::Error> for SyncError {
+    fn from(error: serde_json::Error) -> Self {
+        SyncError::LockfileJsonError(error)
+    }
+}
+
+impl From<rusqlite::Error> for SyncError {
+    fn from(error: rusqlite::Error) -> Self {
+        SyncError::SqliteError(error)
+    }
+}
+
 impl From<StreamingFastProtosError> for EraValidateError {
     fn from(error: StreamingFastProtosError) -> Self {
         match error {
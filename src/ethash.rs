@@ -0,0 +1,297 @@
+use alloy_primitives::{keccak256, B256, U256, U512};
+use ethportal_api::Header;
+use rlp::RlpStream;
+use sha3::{Digest, Keccak512};
+
+use crate::errors::EraValidateError;
+
+/// Number of blocks sharing the same Ethash cache and dataset
+pub const ETHASH_EPOCH_LENGTH: u64 = 30000;
+
+const DATASET_BYTES_INIT: usize = 1 << 30;
+const DATASET_BYTES_GROWTH: usize = 1 << 23;
+const CACHE_BYTES_INIT: usize = 1 << 24;
+const CACHE_BYTES_GROWTH: usize = 1 << 17;
+const MIX_BYTES: usize = 128;
+const HASH_BYTES: usize = 64;
+const DATASET_PARENTS: u32 = 256;
+const CACHE_ROUNDS: usize = 3;
+const ACCESSES: u32 = 64;
+
+/// A 64 byte hash, as little endian 32-bit words
+type Node = [u32; 16];
+
+/// Returns the size in bytes of the light cache of Ethash epoch `epoch`
+pub fn cache_size(epoch: u64) -> usize {
+    let mut size = CACHE_BYTES_INIT + CACHE_BYTES_GROWTH * epoch as usize - HASH_BYTES;
+    while !is_prime(size / HASH_BYTES) {
+        size -= 2 * HASH_BYTES;
+    }
+    size
+}
+
+/// Returns the size in bytes of the full dataset of Ethash epoch `epoch`
+pub fn dataset_size(epoch: u64) -> usize {
+    let mut size = DATASET_BYTES_INIT + DATASET_BYTES_GROWTH * epoch as usize - MIX_BYTES;
+    while !is_prime(size / MIX_BYTES) {
+        size -= 2 * MIX_BYTES;
+    }
+    size
+}
+
+/// Returns the seed of the light cache of Ethash epoch `epoch`
+pub fn seed_hash(epoch: u64) -> B256 {
+    (0..epoch).fold(B256::ZERO, |seed, _| keccak256(seed))
+}
+
+/// Returns the hash sealed by the proof of work of `header`, i.e. the hash of the header
+/// without its mix hash and nonce.
+pub fn seal_hash(header: &Header) -> B256 {
+    let fields = if header.base_fee_per_gas.is_some() {
+        14
+    } else {
+        13
+    };
+    let mut stream = RlpStream::new_list(fields);
+    stream
+        .append(&header.parent_hash.as_slice())
+        .append(&header.uncles_hash.as_slice())
+        .append(&header.author.as_slice())
+        .append(&header.state_root.as_slice())
+        .append(&header.transactions_root.as_slice())
+        .append(&header.receipts_root.as_slice())
+        .append(&header.logs_bloom.as_slice())
+        .append(&header.difficulty.to_be_bytes_trimmed_vec())
+        .append(&header.number)
+        .append(&header.gas_limit.to_be_bytes_trimmed_vec())
+        .append(&header.gas_used.to_be_bytes_trimmed_vec())
+        .append(&header.timestamp)
+        .append(&header.extra_data);
+    if let Some(base_fee_per_gas) = header.base_fee_per_gas {
+        stream.append(&base_fee_per_gas.to_be_bytes_trimmed_vec());
+    }
+    keccak256(stream.out())
+}
+
+/// The Ethash light cache of an epoch, from which any item of the full dataset can be
+/// computed on demand.
+///
+/// Generating a cache takes a few seconds, so it should be reused for every header of
+/// the epoch.
+pub struct LightCache {
+    epoch: u64,
+    nodes: Vec<Node>,
+    dataset_size: usize,
+}
+
+impl LightCache {
+    pub fn new(epoch: u64) -> Self {
+        LightCache {
+            epoch,
+            ..Self::with_sizes(cache_size(epoch), dataset_size(epoch), seed_hash(epoch))
+        }
+    }
+
+    fn with_sizes(cache_size: usize, dataset_size: usize, seed: B256) -> Self {
+        let n = cache_size / HASH_BYTES;
+
+        let mut nodes: Vec<Node> = Vec::with_capacity(n);
+        nodes.push(keccak512(seed.as_slice()));
+        for i in 1..n {
+            let node = hash_node(&nodes[i - 1]);
+            nodes.push(node);
+        }
+
+        for _ in 0..CACHE_ROUNDS {
+            for i in 0..n {
+                let v = nodes[i][0] as usize % n;
+                let mut node = nodes[(i + n - 1) % n];
+                for (word, other) in node.iter_mut().zip(&nodes[v]) {
+                    *word ^= other;
+                }
+                nodes[i] = hash_node(&node);
+            }
+        }
+
+        LightCache {
+            epoch: 0,
+            nodes,
+            dataset_size,
+        }
+    }
+
+    /// The Ethash epoch of the cache
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    fn dataset_item(&self, index: u32) -> Node {
+        let n = self.nodes.len();
+        let mut mix = self.nodes[index as usize % n];
+        mix[0] ^= index;
+        mix = hash_node(&mix);
+
+        for parent in 0..DATASET_PARENTS {
+            let parent = fnv(index ^ parent, mix[parent as usize % 16]) as usize % n;
+            for (word, other) in mix.iter_mut().zip(&self.nodes[parent]) {
+                *word = fnv(*word, *other);
+            }
+        }
+
+        hash_node(&mix)
+    }
+
+    /// Computes the mix digest and the proof of work result of `nonce` for the seal hash
+    /// `header_hash`.
+    pub fn hashimoto(&self, header_hash: B256, nonce: u64) -> (B256, B256) {
+        let mut seed = [0u8; 40];
+        seed[..32].copy_from_slice(header_hash.as_slice());
+        seed[32..].copy_from_slice(&nonce.to_le_bytes());
+        let seed = keccak512(&seed);
+
+        let mut mix = [0u32; MIX_BYTES / 4];
+        mix[..16].copy_from_slice(&seed);
+        mix[16..].copy_from_slice(&seed);
+
+        let rows = (self.dataset_size / MIX_BYTES) as u32;
+        for i in 0..ACCESSES {
+            let row = fnv(i ^ seed[0], mix[i as usize % mix.len()]) % rows;
+            let (first, second) = mix.split_at_mut(16);
+            for (half, index) in [(first, row * 2), (second, row * 2 + 1)] {
+                for (word, other) in half.iter_mut().zip(&self.dataset_item(index)) {
+                    *word = fnv(*word, *other);
+                }
+            }
+        }
+
+        let mut digest = [0u8; 32];
+        for (bytes, words) in digest.chunks_exact_mut(4).zip(mix.chunks_exact(4)) {
+            let word = fnv(fnv(fnv(words[0], words[1]), words[2]), words[3]);
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+
+        let mut result = node_to_bytes(&seed).to_vec();
+        result.extend_from_slice(&digest);
+        (B256::from(digest), keccak256(result))
+    }
+}
+
+/// Verifies the Ethash proof of work of pre-merge headers, without the rest of their epoch.
+///
+/// The light cache of the last epoch seen is kept, so headers should be verified in
+/// block order when possible.
+#[derive(Default)]
+pub struct EthashVerifier {
+    cache: Option<LightCache>,
+}
+
+impl EthashVerifier {
+    pub fn new() -> Self {
+        EthashVerifier::default()
+    }
+
+    /// Checks that the mix hash of `header` is the one of its nonce, and that the proof of
+    /// work meets the difficulty of the header.
+    pub fn verify_seal(&mut self, header: &Header) -> Result<(), EraValidateError> {
+        let (Some(mix_hash), Some(nonce)) = (header.mix_hash, header.nonce) else {
+            return Err(EraValidateError::MissingSeal(header.number));
+        };
+        // post-merge headers are not sealed by proof of work
+        if header.difficulty == U256::ZERO {
+            return Err(EraValidateError::MissingSeal(header.number));
+        }
+
+        let epoch = header.number / ETHASH_EPOCH_LENGTH;
+        let cache = match self.cache.take() {
+            Some(cache) if cache.epoch() == epoch => cache,
+            _ => LightCache::new(epoch),
+        };
+        let (digest, result) = cache.hashimoto(seal_hash(header), u64::from_be_bytes(nonce.0));
+        self.cache = Some(cache);
+
+        if digest != mix_hash {
+            return Err(EraValidateError::MixHashMismatch(header.number));
+        }
+
+        let target = (U512::from(1) << 256) / U512::from(header.difficulty);
+        if U512::from_be_slice(result.as_slice()) > target {
+            return Err(EraValidateError::InsufficientProofOfWork(header.number));
+        }
+
+        Ok(())
+    }
+}
+
+fn fnv(a: u32, b: u32) -> u32 {
+    a.wrapping_mul(0x01000193) ^ b
+}
+
+fn keccak512(bytes: &[u8]) -> Node {
+    let hash = Keccak512::digest(bytes);
+    let mut node = [0u32; 16];
+    for (word, bytes) in node.iter_mut().zip(hash.chunks_exact(4)) {
+        *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    node
+}
+
+fn node_to_bytes(node: &Node) -> [u8; HASH_BYTES] {
+    let mut bytes = [0u8; HASH_BYTES];
+    for (chunk, word) in bytes.chunks_exact_mut(4).zip(node) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    bytes
+}
+
+fn hash_node(node: &Node) -> Node {
+    keccak512(&node_to_bytes(node))
+}
+
+fn is_prime(n: usize) -> bool {
+    n >= 2
+        && (2..)
+            .take_while(|i| i * i <= n)
+            .all(|i| !n.is_multiple_of(i))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::b256;
+
+    #[test]
+    fn test_sizes() {
+        assert_eq!(cache_size(0), 16776896);
+        assert_eq!(cache_size(1), 16907456);
+        assert_eq!(dataset_size(0), 1073739904);
+        assert_eq!(dataset_size(1), 1082130304);
+    }
+
+    #[test]
+    fn test_seed_hash() {
+        assert_eq!(seed_hash(0), B256::ZERO);
+        assert_eq!(
+            seed_hash(1),
+            b256!("290decd9548b62a8d60345a988386fc84ba6bc95484008f6362f93160ef3e563")
+        );
+    }
+
+    #[test]
+    fn test_hashimoto() {
+        // mainnet block 1
+        let cache = LightCache::new(0);
+        let (digest, result) = cache.hashimoto(
+            b256!("85913a3057ea8bec78cd916871ca73802e77724e014dda65add3405d02240eb7"),
+            0x539bd4979fef1ec4,
+        );
+
+        assert_eq!(
+            digest,
+            b256!("969b900de27b6ac6a67742365dd65f55a0526c41fd18e1b16f1a1215c2e66f59")
+        );
+        assert_eq!(
+            result,
+            b256!("000000002bc095dd4de049873e6302c3f14a7f2e5b5a1f60cdf1f1798164d610")
+        );
+    }
+}
@@ -1,27 +1,37 @@
-use crate::{epoch::MAX_EPOCH_SIZE, errors::EraValidateError, types::ExtHeaderRecord};
+use crate::{
+    epoch::{
+        epoch_block_range, epoch_of_block, epochs_covering, is_pre_merge, offset_in_epoch,
+        pre_merge_epoch_size, FINAL_EPOCH, MAX_EPOCH_SIZE,
+    },
+    errors::EraValidateError,
+    types::{pre_merge_accumulator_id, ExtHeaderRecord},
+};
 
-use alloy_primitives::FixedBytes;
+use alloy_primitives::{FixedBytes, B256};
+use ethereum_hashing::hash32_concat;
 use ethportal_api::{
-    types::execution::{
-        accumulator::{EpochAccumulator, HeaderRecord},
-        header_with_proof::{BlockHeaderProof, HeaderWithProof, PreMergeAccumulatorProof},
-    },
+    types::execution::accumulator::{EpochAccumulator, HeaderRecord},
     Header,
 };
+use serde::{Deserialize, Serialize};
 use sf_protos::ethereum::r#type::v2::Block;
-use trin_validation::{
-    accumulator::PreMergeAccumulator, header_validator::HeaderValidator,
-    historical_roots_acc::HistoricalRootsAccumulator,
+use ssz_derive::{Decode, Encode};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
 };
+use trin_validation::accumulator::PreMergeAccumulator;
 
 /// generates an inclusion proof over headers, given blocks between `start_block` and `end_block`
 ///
 /// # Arguments
 ///
-/// * `ext_headers`-  A mutable [`Vec<ExtHeaderRecord>`]. The Vector can be any size, however, it must be in chunks of 8192 blocks to work properly
-///   to function without error
+/// * `ext_headers`-  A mutable [`Vec<ExtHeaderRecord>`]. The first header must be the first block of an epoch, and the
+///   headers must cover every epoch containing a block between `start_block` and `end_block`, i.e. hold the
+///   [`pre_merge_epoch_size`] headers of each of them. They do not need to start at epoch 0.
 /// * `start_block` -  The starting point of blocks that are to be included in the proofs. This interval is inclusive.
-/// * `end_epoch` -  The ending point of blocks that are to be included in the proofs. This interval is inclusive.
+/// * `end_block` -  The ending point of blocks that are to be included in the proofs. This interval is inclusive,
+///   and must end before [`crate::epoch::MERGE_BLOCK`], as only pre-merge headers are part of the accumulator.
 pub fn generate_inclusion_proof(
     mut ext_headers: Vec<ExtHeaderRecord>,
     start_block: u64,
@@ -32,11 +42,51 @@ pub fn generate_inclusion_proof(
     }
 
     // Compute the epoch accumulator for the blocks
-    // The epochs start on a multiple of 8192 blocks, so we need to round down to the nearest 8192
-    let epoch_start = start_block / MAX_EPOCH_SIZE as u64;
+    // The epochs start and end on multiples of 8192 blocks, so the range is widened to whole epochs
+    let epochs = epochs_covering(start_block, end_block);
+    let (epoch_start, epoch_end) = (*epochs.start(), *epochs.end());
+
+    if !is_pre_merge(end_block) {
+        return Err(if epoch_end > FINAL_EPOCH {
+            EraValidateError::EpochAfterMerge(epoch_end)
+        } else {
+            EraValidateError::BlockAfterMerge(end_block)
+        });
+    }
+
+    // The supplied headers may start at any epoch, as long as they start on its first block
+    let supplied_epoch_start = match ext_headers.first() {
+        Some(header) if offset_in_epoch(header.block_number) == 0 => {
+            epoch_of_block(header.block_number)
+        }
+        Some(_) => return Err(EraValidateError::InvalidEpochStart),
+        None => {
+            return Err(EraValidateError::BlockRangeNotCovered(
+                start_block,
+                end_block,
+            ))
+        }
+    };
 
-    // The epochs end on a multiple of 8192 blocks, so we need to round up to the nearest 8192
-    let epoch_end = ((end_block as f32) / MAX_EPOCH_SIZE as f32).ceil() as u64;
+    // The final epoch only holds the headers preceding the merge
+    if epoch_start < supplied_epoch_start
+        || ext_headers.len()
+            < (supplied_epoch_start..=epoch_end)
+                .map(pre_merge_epoch_size)
+                .sum::<usize>()
+    {
+        return Err(EraValidateError::BlockRangeNotCovered(
+            start_block,
+            end_block,
+        ));
+    }
+
+    // Skip the supplied epochs that precede the requested range
+    ext_headers.drain(
+        0..(supplied_epoch_start..epoch_start)
+            .map(pre_merge_epoch_size)
+            .sum(),
+    );
 
     // We need to load blocks from an entire epoch to be able to generate inclusion proofs
     // First compute epoch accumulators and the Merkle tree for all the epochs of interest
@@ -44,8 +94,20 @@ pub fn generate_inclusion_proof(
     let mut inclusion_proof_vec: Vec<[FixedBytes<32>; 15]> = Vec::new();
     let mut headers: Vec<Header> = Vec::new();
 
-    for _ in epoch_start..epoch_end {
-        let epoch_headers: Vec<ExtHeaderRecord> = ext_headers.drain(0..MAX_EPOCH_SIZE).collect();
+    for epoch in epochs {
+        let epoch_headers: Vec<ExtHeaderRecord> =
+            ext_headers.drain(0..pre_merge_epoch_size(epoch)).collect();
+
+        // headers are indexed by their offset from the start of the range, so they must be contiguous
+        for (header, expected) in epoch_headers.iter().zip(epoch_block_range(epoch)) {
+            if header.block_number != expected {
+                return Err(EraValidateError::UnexpectedBlockNumber(
+                    expected,
+                    header.block_number,
+                ));
+            }
+        }
+
         let header_records: Vec<HeaderRecord> = epoch_headers.iter().map(Into::into).collect();
         let tmp_headers: Vec<Header> = epoch_headers
             .into_iter()
@@ -55,21 +117,55 @@ pub fn generate_inclusion_proof(
         epoch_accumulators.push(EpochAccumulator::from(header_records));
     }
 
-    for block_idx in start_block..=end_block {
-        let epoch = block_idx / MAX_EPOCH_SIZE as u64;
-        let epoch_acc = epoch_accumulators[epoch as usize].clone();
-        let header = headers[block_idx as usize].clone();
+    // only the last epoch of the range can be the final one, so every preceding epoch is whole
+    for block_number in start_block..=end_block {
+        let epoch_idx = epoch_of_block(block_number) - epoch_start;
+        let header_idx = epoch_idx * MAX_EPOCH_SIZE + offset_in_epoch(block_number);
         inclusion_proof_vec.push(
-            PreMergeAccumulator::construct_proof(&header, &epoch_acc)
-                .map_err(|_| EraValidateError::ProofGenerationFailure)?,
+            PreMergeAccumulator::construct_proof(
+                &headers[header_idx],
+                &epoch_accumulators[epoch_idx],
+            )
+            .map_err(|_| EraValidateError::ProofGenerationFailure)?,
         );
     }
 
     Ok(inclusion_proof_vec)
 }
 
+/// generates an [`InclusionProofBundle`] for every block between `start_block` and `end_block`
+///
+/// Takes the same arguments as [`generate_inclusion_proof`], plus the [`PreMergeAccumulator`]
+/// the proofs are generated against, which is recorded in every bundle.
+pub fn generate_inclusion_proof_bundles(
+    ext_headers: Vec<ExtHeaderRecord>,
+    start_block: u64,
+    end_block: u64,
+    pre_merge_accumulator: &PreMergeAccumulator,
+) -> Result<Vec<InclusionProofBundle>, EraValidateError> {
+    let block_hashes: Vec<(u64, B256)> = ext_headers
+        .iter()
+        .filter(|header| (start_block..=end_block).contains(&header.block_number))
+        .map(|header| (header.block_number, header.block_hash))
+        .collect();
+
+    // the headers in range are contiguous once proofs are generated, so they line up with them
+    let inclusion_proof = generate_inclusion_proof(ext_headers, start_block, end_block)?;
+    let accumulator = pre_merge_accumulator_id(pre_merge_accumulator);
+
+    Ok(block_hashes
+        .into_iter()
+        .zip(inclusion_proof)
+        .map(|((block_number, block_hash), proof)| {
+            InclusionProofBundle::new(block_number, block_hash, proof, accumulator)
+        })
+        .collect())
+}
+
 /// verifies an inclusion proof generate by [`generate_inclusion_proof`]
 ///
+/// Stops at the first block that fails, use [`verify_inclusion_proof_report`] to check every block.
+///
 /// * `blocks`-  A [`Vec<Block>`]. The blocks included in the inclusion proof interval, set in `start_block` and `end_block` of [`generate_inclusion_proof`]
 /// * `pre_merge_accumulator_file`- An instance of [`PreMergeAccumulator`] which is a file that maintains a record of historical epoch
 ///   it is used to verify canonical-ness of headers accumulated from the `blocks`
@@ -79,27 +175,473 @@ pub fn verify_inclusion_proof(
     pre_merge_accumulator_file: Option<PreMergeAccumulator>,
     inclusion_proof: Vec<[FixedBytes<32>; 15]>,
 ) -> Result<(), EraValidateError> {
-    let pre_merge_acc = pre_merge_accumulator_file.unwrap_or_default();
+    verify_inclusion_proof_report(&blocks, pre_merge_accumulator_file, &inclusion_proof, true)
+        .into_result()
+}
+
+/// verifies an inclusion proof generate by [`generate_inclusion_proof`] using only block headers
+///
+/// Same as [`verify_inclusion_proof`], for verifiers that only hold headers, e.g. decoded from RLP
+/// or fetched over JSON-RPC. An [`ExtHeaderRecord`] with a full header can be converted with
+/// [`TryInto<Header>`].
+///
+/// * `headers`-  A [`Vec<Header>`]. The headers included in the inclusion proof interval, set in `start_block` and `end_block` of [`generate_inclusion_proof`]
+/// * `pre_merge_accumulator_file`- An instance of [`PreMergeAccumulator`] which is a file that maintains a record of historical epoch
+///   it is used to verify canonical-ness of `headers`
+/// * `inclusion_proof` -  The inclusion proof generated from [`generate_inclusion_proof`].
+pub fn verify_inclusion_proof_from_headers(
+    headers: Vec<Header>,
+    pre_merge_accumulator_file: Option<PreMergeAccumulator>,
+    inclusion_proof: Vec<[FixedBytes<32>; 15]>,
+) -> Result<(), EraValidateError> {
+    verify_inclusion_proof_from_headers_report(
+        headers,
+        pre_merge_accumulator_file,
+        &inclusion_proof,
+        true,
+    )
+    .into_result()
+}
+
+/// verifies an inclusion proof generate by [`generate_inclusion_proof`], reporting the outcome of every block
+///
+/// Takes the same arguments as [`verify_inclusion_proof`]. `blocks` and `inclusion_proof` are
+/// matched by position, blocks without a proof fail with [`ProofFailureReason::LengthMismatch`].
+/// When `fail_fast` is set, verification stops at the first failing block.
+pub fn verify_inclusion_proof_report(
+    blocks: &[Block],
+    pre_merge_accumulator_file: Option<PreMergeAccumulator>,
+    inclusion_proof: &[[FixedBytes<32>; 15]],
+    fail_fast: bool,
+) -> InclusionProofReport {
+    let headers = blocks.iter().map(|block| {
+        (
+            block.number,
+            Header::try_from(block).map_err(|_| ProofFailureReason::HeaderDecodeError),
+        )
+    });
+
+    build_report(
+        headers,
+        blocks.len(),
+        pre_merge_accumulator_file,
+        inclusion_proof,
+        fail_fast,
+    )
+}
+
+/// verifies an inclusion proof generate by [`generate_inclusion_proof`] using only block headers,
+/// reporting the outcome of every header
+///
+/// See [`verify_inclusion_proof_report`].
+pub fn verify_inclusion_proof_from_headers_report(
+    headers: Vec<Header>,
+    pre_merge_accumulator_file: Option<PreMergeAccumulator>,
+    inclusion_proof: &[[FixedBytes<32>; 15]],
+    fail_fast: bool,
+) -> InclusionProofReport {
+    let header_count = headers.len();
+    let headers = headers
+        .into_iter()
+        .map(|header| (header.number, Ok(header)));
+
+    build_report(
+        headers,
+        header_count,
+        pre_merge_accumulator_file,
+        inclusion_proof,
+        fail_fast,
+    )
+}
 
-    let header_validator = HeaderValidator {
-        pre_merge_acc,
-        historical_roots_acc: HistoricalRootsAccumulator::default(),
+/// verifies the [`InclusionProofBundle`]s of `blocks`, reporting the outcome of every block
+///
+/// Unlike [`verify_inclusion_proof_report`], bundles are matched to blocks by block number, so
+/// they can be supplied in any order. Blocks without a bundle fail with
+/// [`ProofFailureReason::MissingBundle`], and bundles of blocks that were not supplied are
+/// counted as unmatched. When `fail_fast` is set, verification stops at the first failing block.
+pub fn verify_inclusion_proof_bundles_report(
+    blocks: &[Block],
+    bundles: &[InclusionProofBundle],
+    verifier: &BundleVerifier,
+    fail_fast: bool,
+) -> InclusionProofReport {
+    let bundles: HashMap<u64, &InclusionProofBundle> = bundles
+        .iter()
+        .map(|bundle| (bundle.block_number, bundle))
+        .collect();
+    let block_numbers: HashSet<u64> = blocks.iter().map(|block| block.number).collect();
+    let mut report = InclusionProofReport {
+        blocks: Vec::with_capacity(blocks.len()),
+        unmatched_proofs: bundles
+            .keys()
+            .filter(|number| !block_numbers.contains(number))
+            .count(),
     };
 
-    for (block_idx, _) in blocks.iter().enumerate() {
-        let bhp = BlockHeaderProof::PreMergeAccumulatorProof(PreMergeAccumulatorProof {
-            proof: inclusion_proof[block_idx],
+    for block in blocks {
+        let result = Header::try_from(block)
+            .map_err(|_| ProofFailureReason::HeaderDecodeError)
+            .and_then(|header| {
+                let bundle = bundles
+                    .get(&block.number)
+                    .ok_or(ProofFailureReason::MissingBundle)?;
+                verifier
+                    .verify(bundle, header)
+                    .map_err(|_| ProofFailureReason::ProofMismatch)
+            });
+
+        let failed = result.is_err();
+        report.blocks.push(BlockVerification {
+            block_number: block.number,
+            result,
         });
 
-        let hwp = HeaderWithProof {
-            header: Header::try_from(&blocks[block_idx])?,
-            proof: bhp,
-        };
+        if failed && fail_fast {
+            break;
+        }
+    }
+
+    report
+}
 
-        header_validator
-            .validate_header_with_proof(&hwp)
-            .map_err(|_| EraValidateError::ProofValidationFailure)?;
+fn build_report(
+    headers: impl Iterator<Item = (u64, Result<Header, ProofFailureReason>)>,
+    header_count: usize,
+    pre_merge_accumulator_file: Option<PreMergeAccumulator>,
+    inclusion_proof: &[[FixedBytes<32>; 15]],
+    fail_fast: bool,
+) -> InclusionProofReport {
+    let pre_merge_accumulator = pre_merge_accumulator_file.unwrap_or_default();
+    let mut report = InclusionProofReport {
+        blocks: Vec::with_capacity(header_count),
+        unmatched_proofs: inclusion_proof.len().saturating_sub(header_count),
+    };
+
+    for (block_idx, (block_number, header)) in headers.enumerate() {
+        let result = header.and_then(|header| {
+            let proof = inclusion_proof
+                .get(block_idx)
+                .ok_or(ProofFailureReason::LengthMismatch)?;
+            verify_header_proof(&pre_merge_accumulator, &header, proof)
+                .map_err(|_| ProofFailureReason::ProofMismatch)
+        });
+
+        let failed = result.is_err();
+        report.blocks.push(BlockVerification {
+            block_number,
+            result,
+        });
+
+        if failed && fail_fast {
+            break;
+        }
     }
 
+    report
+}
+
+/// The reason a block failed inclusion proof verification
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofFailureReason {
+    /// There is no proof for the block
+    LengthMismatch,
+    /// There is no proof bundle for the block
+    MissingBundle,
+    /// The header could not be decoded from the block
+    HeaderDecodeError,
+    /// The proof does not show the header is part of the accumulator
+    ProofMismatch,
+}
+
+impl fmt::Display for ProofFailureReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::LengthMismatch => write!(f, "no inclusion proof for block"),
+            Self::MissingBundle => write!(f, "no inclusion proof bundle for block"),
+            Self::HeaderDecodeError => write!(f, "error decoding header from block"),
+            Self::ProofMismatch => write!(f, "inclusion proof does not match header"),
+        }
+    }
+}
+
+/// The outcome of verifying a single block
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockVerification {
+    pub block_number: u64,
+    pub result: Result<(), ProofFailureReason>,
+}
+
+/// Per-block outcome of verifying an inclusion proof
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InclusionProofReport {
+    /// Verified blocks, in the order they were supplied
+    pub blocks: Vec<BlockVerification>,
+    /// Number of proofs supplied beyond the number of blocks
+    pub unmatched_proofs: usize,
+}
+
+impl InclusionProofReport {
+    /// Returns true if every block was verified and every proof was used
+    pub fn is_valid(&self) -> bool {
+        self.unmatched_proofs == 0 && self.blocks.iter().all(|block| block.result.is_ok())
+    }
+
+    /// Returns the blocks that failed verification
+    pub fn failures(&self) -> impl Iterator<Item = &BlockVerification> {
+        self.blocks.iter().filter(|block| block.result.is_err())
+    }
+
+    /// Converts the report into the error of its first failure, if any
+    pub fn into_result(self) -> Result<(), EraValidateError> {
+        match self.failures().next().map(|block| block.result) {
+            Some(Err(ProofFailureReason::HeaderDecodeError)) => {
+                Err(EraValidateError::HeaderDecodeError)
+            }
+            Some(_) => Err(EraValidateError::ProofValidationFailure),
+            None if self.unmatched_proofs > 0 => Err(EraValidateError::ProofValidationFailure),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The version of the [`InclusionProofBundle`] format produced by this crate.
+pub const INCLUSION_PROOF_BUNDLE_VERSION: u8 = 1;
+
+/// An inclusion proof for a single block, with everything needed to verify it
+/// independently of the headers it was generated from.
+///
+/// Bundles can be encoded as JSON, through serde, or as SSZ. The SSZ encoding is fixed size,
+/// with the version first, so bundles of other versions are rejected when they are verified.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct InclusionProofBundle {
+    /// Format version, see [`INCLUSION_PROOF_BUNDLE_VERSION`]
+    pub version: u8,
+    pub block_number: u64,
+    pub block_hash: B256,
+    /// Index of the epoch containing the block in the pre-merge accumulator
+    pub epoch: u64,
+    #[ssz(with = "proof_ssz")]
+    pub proof: [B256; 15],
+    /// Identifier of the pre-merge accumulator the proof was generated against,
+    /// as returned by [`pre_merge_accumulator_id`]
+    pub accumulator: B256,
+}
+
+impl InclusionProofBundle {
+    pub fn new(block_number: u64, block_hash: B256, proof: [B256; 15], accumulator: B256) -> Self {
+        InclusionProofBundle {
+            version: INCLUSION_PROOF_BUNDLE_VERSION,
+            block_number,
+            block_hash,
+            epoch: epoch_of_block(block_number) as u64,
+            proof,
+            accumulator,
+        }
+    }
+
+    /// Verifies that `header` is the block this bundle was generated for, and that the
+    /// proof shows it is part of `pre_merge_accumulator`.
+    ///
+    /// This hashes the whole accumulator, so use a [`BundleVerifier`] to verify many bundles.
+    pub fn verify(
+        &self,
+        header: Header,
+        pre_merge_accumulator: &PreMergeAccumulator,
+    ) -> Result<(), EraValidateError> {
+        BundleVerifier::new(pre_merge_accumulator).verify(self, header)
+    }
+}
+
+/// Verifies [`InclusionProofBundle`]s against a pre-merge accumulator, whose identifier is
+/// computed once rather than for every bundle.
+pub struct BundleVerifier<'a> {
+    pre_merge_accumulator: &'a PreMergeAccumulator,
+    accumulator_id: B256,
+}
+
+impl<'a> BundleVerifier<'a> {
+    pub fn new(pre_merge_accumulator: &'a PreMergeAccumulator) -> Self {
+        BundleVerifier {
+            pre_merge_accumulator,
+            accumulator_id: pre_merge_accumulator_id(pre_merge_accumulator),
+        }
+    }
+
+    /// Identifier of the accumulator, as returned by [`pre_merge_accumulator_id`]
+    pub fn accumulator_id(&self) -> B256 {
+        self.accumulator_id
+    }
+
+    /// Verifies that `header` is the block `bundle` was generated for, and that the proof
+    /// shows it is part of the accumulator.
+    pub fn verify(
+        &self,
+        bundle: &InclusionProofBundle,
+        header: Header,
+    ) -> Result<(), EraValidateError> {
+        if bundle.version != INCLUSION_PROOF_BUNDLE_VERSION {
+            return Err(EraValidateError::UnsupportedProofBundleVersion(
+                bundle.version,
+            ));
+        }
+
+        if bundle.accumulator != self.accumulator_id
+            || bundle.block_number != header.number
+            || bundle.epoch != epoch_of_block(header.number) as u64
+            || bundle.block_hash != header.hash()
+        {
+            return Err(EraValidateError::ProofValidationFailure);
+        }
+
+        verify_header_proof(self.pre_merge_accumulator, &header, &bundle.proof)
+    }
+}
+
+/// SSZ encoding of the proof of an [`InclusionProofBundle`], as a vector of its nodes
+mod proof_ssz {
+    pub mod encode {
+        use alloy_primitives::B256;
+        use ssz::Encode;
+
+        pub fn is_ssz_fixed_len() -> bool {
+            true
+        }
+
+        pub fn ssz_fixed_len() -> usize {
+            15 * <B256 as Encode>::ssz_fixed_len()
+        }
+
+        pub fn ssz_bytes_len(_proof: &[B256; 15]) -> usize {
+            ssz_fixed_len()
+        }
+
+        pub fn ssz_append(proof: &[B256; 15], buf: &mut Vec<u8>) {
+            for node in proof {
+                node.ssz_append(buf);
+            }
+        }
+    }
+
+    pub mod decode {
+        use alloy_primitives::B256;
+        use ssz::{Decode, DecodeError};
+
+        pub fn is_ssz_fixed_len() -> bool {
+            true
+        }
+
+        pub fn ssz_fixed_len() -> usize {
+            super::encode::ssz_fixed_len()
+        }
+
+        pub fn from_ssz_bytes(bytes: &[u8]) -> Result<[B256; 15], DecodeError> {
+            if bytes.len() != ssz_fixed_len() {
+                return Err(DecodeError::InvalidByteLength {
+                    len: bytes.len(),
+                    expected: ssz_fixed_len(),
+                });
+            }
+
+            let mut proof = [B256::ZERO; 15];
+            let node_len = <B256 as Decode>::ssz_fixed_len();
+            for (node, chunk) in proof.iter_mut().zip(bytes.chunks_exact(node_len)) {
+                *node = B256::from_ssz_bytes(chunk)?;
+            }
+            Ok(proof)
+        }
+    }
+}
+
+/// Checks that `proof` is the merkle branch from the hash of `header` to the root of its epoch
+/// in `pre_merge_acc`, like trin's `HeaderValidator`, which would need to own the accumulator.
+fn verify_header_proof(
+    pre_merge_acc: &PreMergeAccumulator,
+    header: &Header,
+    proof: &[FixedBytes<32>; 15],
+) -> Result<(), EraValidateError> {
+    if !is_pre_merge(header.number) {
+        return Err(EraValidateError::ProofValidationFailure);
+    }
+    let epoch_root = pre_merge_acc
+        .historical_epochs
+        .get(epoch_of_block(header.number))
+        .ok_or(EraValidateError::ProofValidationFailure)?;
+
+    // the block hash is the first field of its header record, so the branch starts with the
+    // total difficulty, and ends with the length of the epoch's list of records
+    let mut index = (MAX_EPOCH_SIZE * 2 + offset_in_epoch(header.number)) * 2;
+    let mut node = header.hash();
+    for sibling in proof {
+        node = if index.is_multiple_of(2) {
+            hash32_concat(node.as_slice(), sibling.as_slice())
+        } else {
+            hash32_concat(sibling.as_slice(), node.as_slice())
+        }
+        .into();
+        index /= 2;
+    }
+
+    if index != 1 || node != *epoch_root {
+        return Err(EraValidateError::ProofValidationFailure);
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ssz::{Decode, DecodeError, Encode};
+
+    #[test]
+    fn test_bundle_ssz_roundtrip() {
+        let mut proof = [B256::ZERO; 15];
+        for (i, node) in proof.iter_mut().enumerate() {
+            *node = B256::repeat_byte(i as u8 + 1);
+        }
+        let bundle = InclusionProofBundle::new(
+            12_000_000,
+            B256::repeat_byte(0xaa),
+            proof,
+            B256::repeat_byte(0xbb),
+        );
+        assert_eq!(bundle.epoch, 1464);
+
+        // the fields in declaration order, integers being little endian
+        let bytes = bundle.as_ssz_bytes();
+        assert_eq!(bytes.len(), 1 + 8 + 32 + 8 + 15 * 32 + 32);
+        assert_eq!(bytes[0], INCLUSION_PROOF_BUNDLE_VERSION);
+        assert_eq!(bytes[1..9], 12_000_000u64.to_le_bytes());
+        assert_eq!(bytes[49..81], [1; 32]);
+        assert_eq!(
+            InclusionProofBundle::from_ssz_bytes(&bytes).unwrap(),
+            bundle
+        );
+
+        let json = serde_json::to_string(&bundle).unwrap();
+        assert_eq!(
+            serde_json::from_str::<InclusionProofBundle>(&json).unwrap(),
+            bundle
+        );
+    }
+
+    #[test]
+    fn test_bundle_ssz_invalid() {
+        let bundle = InclusionProofBundle::new(0, B256::ZERO, [B256::ZERO; 15], B256::ZERO);
+        let mut bytes = bundle.as_ssz_bytes();
+
+        assert!(matches!(
+            InclusionProofBundle::from_ssz_bytes(&bytes[1..]),
+            Err(DecodeError::InvalidByteLength { .. })
+        ));
+
+        // other versions decode, to be rejected when verified
+        bytes[0] = INCLUSION_PROOF_BUNDLE_VERSION + 1;
+        assert_eq!(
+            InclusionProofBundle::from_ssz_bytes(&bytes)
+                .unwrap()
+                .version,
+            INCLUSION_PROOF_BUNDLE_VERSION + 1
+        );
+    }
+}
@@ -1,6 +1,15 @@
+pub mod audit;
+pub mod body;
+pub mod chain;
+pub mod consensus;
+pub mod diagnostics;
 pub mod epoch;
 pub mod era_validator;
 pub mod errors;
+pub mod ethash;
 pub mod inclusion_proof;
+pub mod stream;
 pub mod sync;
+pub mod total_difficulty;
+pub mod trie;
 pub mod types;
@@ -1,6 +1,33 @@
-use clap::{Arg, Command, Parser, Subcommand};
-use header_accumulator::errors::EraValidateError;
-use std::{io::BufReader, process};
+use alloy_primitives::B256;
+use clap::{Arg, ArgAction, Command, Parser, Subcommand};
+use decoder::decode_flat_files;
+use ethportal_api::{types::execution::accumulator::EpochAccumulator, Header};
+use header_accumulator::{
+    audit::audit_lock,
+    body::verify_body,
+    chain::ChainValidator,
+    consensus::HeaderRuleValidator,
+    diagnostics::{decode_epoch_accumulator, find_first_mismatch},
+    epoch::{epoch_block_range, is_pre_merge},
+    era_validator::EraValidateConfig,
+    errors::{EraValidateError, SyncError},
+    ethash::EthashVerifier,
+    inclusion_proof::{
+        generate_inclusion_proof_bundles, verify_inclusion_proof_bundles_report, BundleVerifier,
+        InclusionProofBundle,
+    },
+    stream::stream_era_validate,
+    sync::Lock,
+    total_difficulty::TotalDifficultyTracker,
+    types::ExtHeaderRecord,
+};
+use sf_protos::ethereum::r#type::v2::Block;
+use std::{
+    io::BufReader,
+    path::{Path, PathBuf},
+    process,
+};
+use tree_hash::TreeHash;
 use trin_validation::accumulator::PreMergeAccumulator;
 
 #[derive(Parser, Debug)]
@@ -74,6 +101,48 @@ fn main() {
                                 .required(false)
                                 .short('m')
                                 .long("pre_merge_accumulator_file"),
+                        )
+                        .arg(
+                            Arg::new("decompress")
+                                .help("Decompress zstd compressed flat files from stdin")
+                                .required(false)
+                                .short('d')
+                                .long("decompress")
+                                .action(ArgAction::SetTrue),
+                        )
+                        .arg(
+                            Arg::new("end_block")
+                                .help("Stop reading once this block number is passed (optional)")
+                                .required(false)
+                                .short('e')
+                                .long("end_block"),
+                        )
+                        .arg(
+                            Arg::new("verify_header_hashes")
+                                .help("Check the block hash of every header against its RLP encoding")
+                                .required(false)
+                                .long("verify_header_hashes")
+                                .action(ArgAction::SetTrue),
+                        )
+                        .arg(
+                            Arg::new("lockfile")
+                                .help("Lockfile recording validated epochs, which are skipped (optional)")
+                                .required(false)
+                                .short('l')
+                                .long("lockfile"),
+                        )
+                        .arg(
+                            Arg::new("derive_total_difficulty")
+                                .help("Derive the total difficulty of the blocks from their difficulty, for flat files without it. The stream must then start at genesis")
+                                .required(false)
+                                .long("derive_total_difficulty")
+                                .action(ArgAction::SetTrue),
+                        )
+                        .arg(
+                            Arg::new("total_difficulty_checkpoint")
+                                .help("Derive the total difficulty of the blocks from the one of the block preceding the stream, given as <block number>:<total difficulty> (optional)")
+                                .required(false)
+                                .long("total_difficulty_checkpoint"),
                         ),
                 ),
         )
@@ -104,6 +173,13 @@ fn main() {
                         .required(false)
                         .short('o')
                         .long("output_file"),
+                )
+                .arg(
+                    Arg::new("pre_merge_accumulator_file")
+                        .help("pre-merge accumulator file (optional)")
+                        .required(false)
+                        .short('m')
+                        .long("pre_merge_accumulator_file"),
                 ),
         )
         .subcommand(
@@ -132,6 +208,141 @@ fn main() {
                         .help("Inclusion proof to verify")
                         .required(true)
                         .index(4),
+                )
+                .arg(
+                    Arg::new("pre_merge_accumulator_file")
+                        .help("pre-merge accumulator file (optional)")
+                        .required(false)
+                        .short('m')
+                        .long("pre_merge_accumulator_file"),
+                ),
+        )
+        .subcommand(
+            Command::new("diagnose_epoch")
+                .about("Finds the first header of an epoch that differs from a reference epoch accumulator")
+                .arg(
+                    Arg::new("directory")
+                        .help("Directory where the flat files are stored")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("epoch")
+                        .help("Epoch to diagnose")
+                        .required(true)
+                        .index(2),
+                )
+                .arg(
+                    Arg::new("epoch_accumulator_file")
+                        .help("SSZ encoded reference epoch accumulator")
+                        .required(true)
+                        .index(3),
+                )
+                .arg(
+                    Arg::new("pre_merge_accumulator_file")
+                        .help("pre-merge accumulator file (optional)")
+                        .required(false)
+                        .short('m')
+                        .long("pre_merge_accumulator_file"),
+                ),
+        )
+        .subcommand(
+            Command::new("validate_chain")
+                .about("Checks that the headers of flat files form a chain, including post-merge blocks")
+                .arg(
+                    Arg::new("directory")
+                        .help("Directory where the flat files are stored")
+                        .required(true)
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            Command::new("check_header_rules")
+                .about("Checks the difficulty, gas limit, timestamp, extra data and base fee of consecutive headers of flat files")
+                .arg(
+                    Arg::new("directory")
+                        .help("Directory where the flat files are stored")
+                        .required(true)
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            Command::new("verify_seal")
+                .about("Verifies the Ethash proof of work of the headers of flat files, without requiring whole epochs")
+                .arg(
+                    Arg::new("directory")
+                        .help("Directory where the flat files are stored")
+                        .required(true)
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            Command::new("verify_bodies")
+                .about("Checks the transactions, receipts and uncles of flat files against their headers")
+                .arg(
+                    Arg::new("directory")
+                        .help("Directory where the flat files are stored")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("chain_id")
+                        .help("Chain id signed into typed transactions")
+                        .required(false)
+                        .short('c')
+                        .long("chain_id")
+                        .default_value("1"),
+                ),
+        )
+        .subcommand(
+            Command::new("audit")
+                .about("Checks every epoch recorded in a lockfile against the pre-merge accumulator")
+                .arg(
+                    Arg::new("lockfile")
+                        .help("Lockfile to audit")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("pre_merge_accumulator_file")
+                        .help("pre-merge accumulator file (optional)")
+                        .required(false)
+                        .short('m')
+                        .long("pre_merge_accumulator_file"),
+                ),
+        )
+        .subcommand(
+            Command::new("merge_lockfiles")
+                .about("Merges lockfiles produced by different validators into one")
+                .arg(
+                    Arg::new("lockfiles")
+                        .help("Lockfiles to merge")
+                        .required(true)
+                        .num_args(1..)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("output_file")
+                        .help("Output file for the merged lockfile")
+                        .required(true)
+                        .short('o')
+                        .long("output_file"),
+                ),
+        )
+        .subcommand(
+            Command::new("diff_lockfiles")
+                .about("Shows which epochs are recorded by each of two lockfiles")
+                .arg(
+                    Arg::new("lockfile")
+                        .help("First lockfile")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("other_lockfile")
+                        .help("Second lockfile")
+                        .required(true)
+                        .index(2),
                 ),
         )
         .get_matches();
@@ -140,94 +351,507 @@ fn main() {
         // TODO: move this functionality to flat_head
         Some(("era_validate", era_validate_matches)) => {
             if let Some(("stream", stream_matches)) = era_validate_matches.subcommand() {
-                let pre_merge_accumulator_file =
-                    stream_matches.get_one::<String>("pre_merge_accumulator_file");
-                let _pre_merge_accumulator = match pre_merge_accumulator_file {
-                    Some(pre_merge_accumulator_file) => {
-                        PreMergeAccumulator::try_from_file(pre_merge_accumulator_file.into())
-                            .map_err(|_| EraValidateError::InvalidPreMergeAccumulatorFile)
-                            .expect("Invalid pre-merge accumulator file")
-                    }
-                    None => PreMergeAccumulator::default(),
+                let pre_merge_accumulator = read_pre_merge_accumulator(
+                    stream_matches.get_one::<String>("pre_merge_accumulator_file"),
+                );
+                let decompress = stream_matches.get_flag("decompress");
+                let end_block = stream_matches
+                    .get_one::<String>("end_block")
+                    .map(|end_block| end_block.parse::<u64>().expect("Invalid end block"));
+                // the stream is always read from stdin, so that is all the provenance can record
+                let config = EraValidateConfig::new()
+                    .with_sources(vec!["stdin".to_string()])
+                    .with_header_hash_check(stream_matches.get_flag("verify_header_hashes"));
+                let config = match stream_matches.get_one::<String>("lockfile") {
+                    Some(lockfile) => config.with_lockfile(lockfile),
+                    None => config,
                 };
-                let _reader = BufReader::with_capacity(1 << 32, std::io::stdin().lock());
-                let _writer = std::io::stdout();
-                process::exit(0);
+
+                let total_difficulty =
+                    match stream_matches.get_one::<String>("total_difficulty_checkpoint") {
+                        Some(checkpoint) => {
+                            let (block_number, total_difficulty) = checkpoint
+                                .split_once(':')
+                                .expect("Invalid total difficulty checkpoint");
+                            Some(TotalDifficultyTracker::from_checkpoint(
+                                block_number
+                                    .parse()
+                                    .expect("Invalid checkpoint block number"),
+                                total_difficulty
+                                    .parse()
+                                    .expect("Invalid checkpoint total difficulty"),
+                            ))
+                        }
+                        None if stream_matches.get_flag("derive_total_difficulty") => {
+                            Some(TotalDifficultyTracker::genesis())
+                        }
+                        None => None,
+                    };
+
+                let reader = BufReader::with_capacity(1 << 20, std::io::stdin().lock());
+                let writer = std::io::stdout().lock();
+
+                let result = if decompress {
+                    let reader = zstd::stream::read::Decoder::with_buffer(reader)
+                        .expect("Unable to initialize zstd decoder");
+                    stream_era_validate(
+                        &pre_merge_accumulator,
+                        &config,
+                        reader,
+                        writer,
+                        end_block,
+                        total_difficulty,
+                    )
+                } else {
+                    stream_era_validate(
+                        &pre_merge_accumulator,
+                        &config,
+                        reader,
+                        writer,
+                        end_block,
+                        total_difficulty,
+                    )
+                };
+
+                match result {
+                    Ok(true) => process::exit(0),
+                    Ok(false) => process::exit(1),
+                    Err(e) => {
+                        log::error!("error: {}", e);
+                        process::exit(1);
+                    }
+                }
             }
         }
         //TODO: move this functionality to flat_head
-        // Some(("generate_inclusion_proof", generate_inclusion_proof_matches)) => {
-        //     let directory = generate_inclusion_proof_matches
-        //         .get_one::<String>("directory")
-        //         .expect("Directory is required.");
-        //     let start_block = generate_inclusion_proof_matches
-        //         .get_one::<String>("start_block")
-        //         .expect("Start block is required.");
-        //     let end_block = generate_inclusion_proof_matches
-        //         .get_one::<String>("end_block")
-        //         .expect("End block is required.");
-
-        //     let inclusion_proof = inclusion_proof::generate_inclusion_proof(
-        //         &directory,
-        //         start_block.parse::<usize>().unwrap(),
-        //         end_block.parse::<usize>().unwrap(),
-        //     )
-        //     .expect("Error generating inclusion proof");
-
-        //     let inclusion_proof_serialized = serde_json::to_string(&inclusion_proof).unwrap();
-        //     // write the proof to a file
-        //     // if output_file is not provided, write to inclusion_proof.json
-        //     let output_file = generate_inclusion_proof_matches.get_one::<String>("output_file");
-        //     match output_file {
-        //         Some(output_file) => {
-        //             std::fs::write(output_file.to_owned() + ".json", inclusion_proof_serialized)
-        //                 .expect("Unable to write file");
-        //         }
-        //         None => {
-        //             std::fs::write("inclusion_proof.json", inclusion_proof_serialized)
-        //                 .expect("Unable to write file");
-        //         }
-        //     }
-        //     process::exit(0);
-        // }
-        // Some(("verify_inclusion_proof", verify_inclusion_proof_matches)) => {
-        //     let directory = verify_inclusion_proof_matches
-        //         .get_one::<String>("directory")
-        //         .expect("Directory is required.");
-        //     let start_block = verify_inclusion_proof_matches
-        //         .get_one::<String>("start_block")
-        //         .expect("Start block is required.");
-        //     let end_block = verify_inclusion_proof_matches
-        //         .get_one::<String>("end_block")
-        //         .expect("End block is required.");
-        //     let inclusion_proof_file = verify_inclusion_proof_matches
-        //         .get_one::<String>("inclusion_proof_file")
-        //         .expect("Inclusion proof is required.");
-
-        //     // Load inclusion proof
-        //     let inclusion_proof = std::fs::read_to_string(inclusion_proof_file)
-        //         .expect("Error reading inclusion proof file");
-        //     let inclusion_proof: Vec<[H256; 15]> =
-        //         serde_json::from_str(&inclusion_proof).expect("Error parsing inclusion proof");
-
-        //     let result = inclusion_proof::verify_inclusion_proof(
-        //         &directory,
-        //         None,
-        //         start_block.parse::<usize>().unwrap(),
-        //         end_block.parse::<usize>().unwrap(),
-        //         inclusion_proof,
-        //     );
-
-        //     if result.is_ok() {
-        //         println!("Inclusion proof verified!");
-        //         process::exit(0);
-        //     } else {
-        //         println!("Inclusion proof failed to verify");
-        //         process::exit(1);
-        //     }
-        // }
+        Some(("generate_inclusion_proof", generate_inclusion_proof_matches)) => {
+            let directory = generate_inclusion_proof_matches
+                .get_one::<String>("directory")
+                .expect("Directory is required.");
+            let start_block = generate_inclusion_proof_matches
+                .get_one::<String>("start_block")
+                .expect("Start block is required.")
+                .parse::<u64>()
+                .expect("Invalid start block");
+            let end_block = generate_inclusion_proof_matches
+                .get_one::<String>("end_block")
+                .expect("End block is required.")
+                .parse::<u64>()
+                .expect("Invalid end block");
+
+            let pre_merge_accumulator = read_pre_merge_accumulator(
+                generate_inclusion_proof_matches.get_one::<String>("pre_merge_accumulator_file"),
+            );
+
+            let headers: Vec<ExtHeaderRecord> = read_flat_files_dir(directory)
+                .expect("Error reading flat files")
+                .iter()
+                .map(ExtHeaderRecord::try_from)
+                .collect::<Result<_, _>>()
+                .expect("Error decoding headers");
+
+            let inclusion_proof = generate_inclusion_proof_bundles(
+                headers,
+                start_block,
+                end_block,
+                &pre_merge_accumulator,
+            )
+            .expect("Error generating inclusion proof");
+
+            let inclusion_proof_serialized = serde_json::to_string(&inclusion_proof).unwrap();
+            // write the proof to a file
+            // if output_file is not provided, write to inclusion_proof.json
+            let output_file = generate_inclusion_proof_matches
+                .get_one::<String>("output_file")
+                .map(String::as_str)
+                .unwrap_or("inclusion_proof.json");
+            std::fs::write(output_file, inclusion_proof_serialized).expect("Unable to write file");
+            process::exit(0);
+        }
+        Some(("verify_inclusion_proof", verify_inclusion_proof_matches)) => {
+            let directory = verify_inclusion_proof_matches
+                .get_one::<String>("directory")
+                .expect("Directory is required.");
+            let start_block = verify_inclusion_proof_matches
+                .get_one::<String>("start_block")
+                .expect("Start block is required.")
+                .parse::<u64>()
+                .expect("Invalid start block");
+            let end_block = verify_inclusion_proof_matches
+                .get_one::<String>("end_block")
+                .expect("End block is required.")
+                .parse::<u64>()
+                .expect("Invalid end block");
+            let inclusion_proof_file = verify_inclusion_proof_matches
+                .get_one::<String>("inclusion_proof_file")
+                .expect("Inclusion proof is required.");
+            let pre_merge_accumulator = read_pre_merge_accumulator(
+                verify_inclusion_proof_matches.get_one::<String>("pre_merge_accumulator_file"),
+            );
+
+            // Load inclusion proof
+            let inclusion_proof = std::fs::read_to_string(inclusion_proof_file)
+                .expect("Error reading inclusion proof file");
+            let inclusion_proof: Vec<InclusionProofBundle> =
+                serde_json::from_str(&inclusion_proof).expect("Error parsing inclusion proof");
+
+            let mut blocks: Vec<Block> = read_flat_files_dir(directory)
+                .expect("Error reading flat files")
+                .into_iter()
+                .filter(|block| (start_block..=end_block).contains(&block.number))
+                .collect();
+            blocks.sort_by_key(|block| block.number);
+            blocks.dedup_by_key(|block| block.number);
+            let inclusion_proof: Vec<InclusionProofBundle> = inclusion_proof
+                .into_iter()
+                .filter(|bundle| (start_block..=end_block).contains(&bundle.block_number))
+                .collect();
+
+            let mut verified = true;
+            let mut next_block = start_block;
+            for block_number in blocks
+                .iter()
+                .map(|block| block.number)
+                .chain([end_block + 1])
+            {
+                if next_block < block_number {
+                    println!(
+                        "Blocks {} - {} failed to verify: missing from the flat files",
+                        next_block,
+                        block_number - 1
+                    );
+                    verified = false;
+                }
+                next_block = block_number + 1;
+            }
+
+            let report = verify_inclusion_proof_bundles_report(
+                &blocks,
+                &inclusion_proof,
+                &BundleVerifier::new(&pre_merge_accumulator),
+                false,
+            );
+            for failure in report.failures() {
+                if let Err(reason) = failure.result {
+                    println!(
+                        "Block {} failed to verify: {}",
+                        failure.block_number, reason
+                    );
+                }
+            }
+            verified &= report.is_valid();
+
+            if verified {
+                println!("Inclusion proof verified!");
+                process::exit(0);
+            } else {
+                println!("Inclusion proof failed to verify");
+                process::exit(1);
+            }
+        }
+        Some(("diagnose_epoch", diagnose_matches)) => {
+            let directory = diagnose_matches
+                .get_one::<String>("directory")
+                .expect("Directory is required.");
+            let epoch = diagnose_matches
+                .get_one::<String>("epoch")
+                .expect("Epoch is required.")
+                .parse::<usize>()
+                .expect("Invalid epoch");
+            let epoch_accumulator_file = diagnose_matches
+                .get_one::<String>("epoch_accumulator_file")
+                .expect("Epoch accumulator file is required.");
+            let pre_merge_accumulator = read_pre_merge_accumulator(
+                diagnose_matches.get_one::<String>("pre_merge_accumulator_file"),
+            );
+
+            let reference = std::fs::read(epoch_accumulator_file)
+                .map_err(|_| EraValidateError::IoError)
+                .and_then(|bytes| decode_epoch_accumulator(&bytes))
+                .expect("Error reading epoch accumulator file");
+
+            // a reference that does not match the pre-merge accumulator cannot be trusted
+            let reference_root = EpochAccumulator::from(reference.clone()).tree_hash_root().0;
+            if pre_merge_accumulator
+                .historical_epochs
+                .get(epoch)
+                .map(|root| root.0)
+                != Some(reference_root)
+            {
+                println!(
+                    "Warning: the reference epoch accumulator does not match epoch {} of the pre-merge accumulator",
+                    epoch
+                );
+            }
+
+            let headers: Vec<ExtHeaderRecord> = read_flat_files_dir(directory)
+                .expect("Error reading flat files")
+                .iter()
+                .filter(|block| epoch_block_range(epoch).contains(&block.number))
+                .map(ExtHeaderRecord::try_from)
+                .collect::<Result<_, _>>()
+                .expect("Error decoding headers");
+
+            match find_first_mismatch(&headers, epoch, &reference) {
+                None => {
+                    println!("Epoch {} matches the reference", epoch);
+                    process::exit(0);
+                }
+                Some(mismatch) => {
+                    println!("Block {} differs from the reference", mismatch.block_number);
+                    match (mismatch.expected, mismatch.actual) {
+                        (Some(expected), Some(actual)) => {
+                            println!(
+                                "  hash: expected {}, found {}",
+                                expected.block_hash, actual.block_hash
+                            );
+                            println!(
+                                "  total difficulty: expected {}, found {}",
+                                expected.total_difficulty, actual.total_difficulty
+                            );
+                        }
+                        (Some(_), None) => println!("  the header is missing"),
+                        (None, _) => println!("  the reference has no record for it"),
+                    }
+                    process::exit(1);
+                }
+            }
+        }
+        Some(("validate_chain", validate_chain_matches)) => {
+            let directory = validate_chain_matches
+                .get_one::<String>("directory")
+                .expect("Directory is required.");
+
+            let mut validator = ChainValidator::new();
+            for block in read_flat_files_dir(directory).expect("Error reading flat files") {
+                let result =
+                    ExtHeaderRecord::try_from(&block).and_then(|header| validator.push(&header));
+                if let Err(e) = result {
+                    println!("Chain broken at block {}: {}", block.number, e);
+                    process::exit(1);
+                }
+            }
+
+            match validator.tip() {
+                Some((block_number, block_hash)) => {
+                    println!("Chain valid up to block {} ({})", block_number, block_hash)
+                }
+                None => println!("No blocks found"),
+            }
+            process::exit(0);
+        }
+        Some(("check_header_rules", check_header_rules_matches)) => {
+            let directory = check_header_rules_matches
+                .get_one::<String>("directory")
+                .expect("Directory is required.");
+
+            let mut validator = HeaderRuleValidator::new();
+            for block in read_flat_files_dir(directory).expect("Error reading flat files") {
+                let result =
+                    ExtHeaderRecord::try_from(&block).and_then(|header| validator.push(&header));
+                if let Err(e) = result {
+                    println!("Header rules broken at block {}: {}", block.number, e);
+                    process::exit(1);
+                }
+            }
+
+            println!("Header rules respected!");
+            process::exit(0);
+        }
+        Some(("verify_seal", verify_seal_matches)) => {
+            let directory = verify_seal_matches
+                .get_one::<String>("directory")
+                .expect("Directory is required.");
+
+            let mut verifier = EthashVerifier::new();
+            let mut verified = true;
+            let mut skipped = 0;
+            for block in read_flat_files_dir(directory).expect("Error reading flat files") {
+                // the genesis block and post-merge blocks are not sealed by proof of work
+                if block.number == 0 || !is_pre_merge(block.number) {
+                    skipped += 1;
+                    continue;
+                }
+
+                let result = Header::try_from(&block)
+                    .map_err(EraValidateError::from)
+                    .and_then(|header| verifier.verify_seal(&header));
+                if let Err(e) = result {
+                    println!("Block {} failed to verify: {}", block.number, e);
+                    verified = false;
+                }
+            }
+
+            if skipped > 0 {
+                println!("Skipped {} blocks not sealed by proof of work", skipped);
+            }
+            if verified {
+                println!("Seals verified!");
+                process::exit(0);
+            } else {
+                process::exit(1);
+            }
+        }
+        Some(("verify_bodies", verify_bodies_matches)) => {
+            let directory = verify_bodies_matches
+                .get_one::<String>("directory")
+                .expect("Directory is required.");
+            let chain_id = verify_bodies_matches
+                .get_one::<String>("chain_id")
+                .expect("Chain id has a default value.")
+                .parse::<u64>()
+                .expect("Invalid chain id");
+
+            let mut verified = true;
+            for block in read_flat_files_dir(directory).expect("Error reading flat files") {
+                if let Err(e) = verify_body(&block, chain_id) {
+                    println!("Block {} failed to verify: {}", block.number, e);
+                    verified = false;
+                }
+            }
+
+            if verified {
+                println!("Block bodies verified!");
+                process::exit(0);
+            } else {
+                process::exit(1);
+            }
+        }
+        Some(("audit", audit_matches)) => {
+            let lockfile = audit_matches
+                .get_one::<String>("lockfile")
+                .expect("Lockfile is required.");
+            let pre_merge_accumulator = read_pre_merge_accumulator(
+                audit_matches.get_one::<String>("pre_merge_accumulator_file"),
+            );
+
+            let report = audit_lock(&read_existing_lock(lockfile), &pre_merge_accumulator);
+            print!("{}", report);
+
+            if report.is_consistent() {
+                process::exit(0);
+            } else {
+                process::exit(1);
+            }
+        }
+        Some(("merge_lockfiles", merge_matches)) => {
+            let output_file = merge_matches
+                .get_one::<String>("output_file")
+                .expect("Output file is required.");
+
+            let mut merged = Lock::new();
+            for lockfile in merge_matches
+                .get_many::<String>("lockfiles")
+                .expect("Lockfiles are required.")
+            {
+                let lock = read_existing_lock(lockfile);
+                if let Err(e) = merged.merge(&lock) {
+                    println!("Unable to merge {}: {}", lockfile, e);
+                    if let SyncError::ConflictingRoots(conflicts) = e {
+                        for conflict in conflicts {
+                            println!(
+                                "epoch {}: {} != {}",
+                                conflict.epoch,
+                                B256::from(conflict.ours),
+                                B256::from(conflict.theirs)
+                            );
+                        }
+                    }
+                    process::exit(1);
+                }
+            }
+
+            merged
+                .store_last_state(Path::new(output_file))
+                .expect("Unable to write merged lockfile");
+            process::exit(0);
+        }
+        Some(("diff_lockfiles", diff_matches)) => {
+            let lockfile = diff_matches
+                .get_one::<String>("lockfile")
+                .expect("Lockfile is required.");
+            let other_lockfile = diff_matches
+                .get_one::<String>("other_lockfile")
+                .expect("Other lockfile is required.");
+
+            let diff = read_existing_lock(lockfile)
+                .diff(&read_existing_lock(other_lockfile))
+                .expect("Error reading lockfile entries");
+
+            println!("common epochs: {}", diff.common.len());
+            for epoch in &diff.only_ours {
+                println!("< epoch {}", epoch);
+            }
+            for epoch in &diff.only_theirs {
+                println!("> epoch {}", epoch);
+            }
+            for conflict in &diff.conflicting {
+                println!(
+                    "! epoch {}: {} != {}",
+                    conflict.epoch,
+                    B256::from(conflict.ours),
+                    B256::from(conflict.theirs)
+                );
+            }
+
+            if diff.is_empty() {
+                process::exit(0);
+            } else {
+                process::exit(1);
+            }
+        }
         _ => {
             println!("No subcommand was used");
         }
     }
 }
+
+/// Loads the pre-merge accumulator from `pre_merge_accumulator_file`, falling back to the
+/// default mainnet accumulator.
+fn read_pre_merge_accumulator(pre_merge_accumulator_file: Option<&String>) -> PreMergeAccumulator {
+    match pre_merge_accumulator_file {
+        Some(pre_merge_accumulator_file) => {
+            PreMergeAccumulator::try_from_file(pre_merge_accumulator_file.into())
+                .map_err(|_| EraValidateError::InvalidPreMergeAccumulatorFile)
+                .expect("Invalid pre-merge accumulator file")
+        }
+        None => PreMergeAccumulator::default(),
+    }
+}
+
+/// Reads the lockfile at `lockfile`, exiting if it does not exist rather than creating it.
+fn read_existing_lock(lockfile: &str) -> Lock {
+    if !Path::new(lockfile).exists() {
+        println!("Lockfile {} does not exist", lockfile);
+        process::exit(1);
+    }
+    Lock::from_file(Path::new(lockfile)).expect("Error reading lockfile")
+}
+
+/// Decodes every `.dbin` and `.dbin.zst` flat file in `directory`, in file name order.
+fn read_flat_files_dir(directory: &str) -> Result<Vec<Block>, EraValidateError> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(directory)
+        .map_err(|_| EraValidateError::IoError)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            let name = path.to_string_lossy();
+            name.ends_with(".dbin") || name.ends_with(".dbin.zst")
+        })
+        .collect();
+    paths.sort();
+
+    let mut blocks = Vec::new();
+    for path in paths {
+        let decompress = path.extension().is_some_and(|extension| extension == "zst");
+        let file_blocks = decode_flat_files(
+            path.to_string_lossy().into_owned(),
+            None,
+            None,
+            Some(decompress),
+        )
+        .map_err(|_| EraValidateError::FlatFileDecodeError)?;
+        blocks.extend(file_blocks);
+    }
+
+    Ok(blocks)
+}
@@ -0,0 +1,148 @@
+use std::io::{ErrorKind, Read, Write};
+
+use prost::Message;
+use sf_protos::{bstream::v1::Block as BstreamBlock, ethereum::r#type::v2::Block};
+use trin_validation::accumulator::PreMergeAccumulator;
+
+use crate::{
+    epoch::{is_pre_merge, offset_in_epoch, pre_merge_epoch_size},
+    era_validator::{EpochResult, EpochStatus, EraValidateConfig, StreamingEraValidator},
+    errors::{EraValidateError, HeaderAccumulatorError},
+    total_difficulty::TotalDifficultyTracker,
+    types::ExtHeaderRecord,
+};
+
+/// Reads flat file blocks from `reader` and validates every completed epoch against
+/// `pre_merge_accumulator` with a [`StreamingEraValidator`], writing one line per epoch to
+/// `writer`.
+///
+/// Blocks preceding the first epoch boundary of the stream are skipped, since they cannot form a
+/// complete epoch. A trailing partial epoch cannot be validated either and is reported as
+/// incomplete. The stream stops at the merge, after the last pre-merge epoch.
+///
+/// If `total_difficulty` is set, the total difficulty of every block is derived with it rather
+/// than read from the block, for sources that don't provide it. The tracker must then expect the
+/// first block of the stream.
+///
+/// Returns `Ok(false)` if any epoch failed validation, or if `end_block` is set and the blocks
+/// up to it did not complete their epoch.
+pub fn stream_era_validate<R: Read, W: Write>(
+    pre_merge_accumulator: &PreMergeAccumulator,
+    config: &EraValidateConfig,
+    mut reader: R,
+    mut writer: W,
+    end_block: Option<u64>,
+    mut total_difficulty: Option<TotalDifficultyTracker>,
+) -> Result<bool, HeaderAccumulatorError> {
+    let mut all_valid = true;
+    let mut validator = StreamingEraValidator::new(pre_merge_accumulator, config.clone());
+
+    while let Some(block) = read_block(&mut reader)? {
+        let header = match total_difficulty.as_mut() {
+            Some(tracker) => tracker.record_block(&block)?,
+            None => ExtHeaderRecord::try_from(&block)?,
+        };
+
+        if end_block.is_some_and(|end_block| header.block_number > end_block) {
+            break;
+        }
+
+        if !is_pre_merge(header.block_number) {
+            log::warn!(
+                "stopping at block {}, it is post merge",
+                header.block_number
+            );
+            break;
+        }
+
+        if validator.buffered() == 0 && offset_in_epoch(header.block_number) != 0 {
+            log::warn!(
+                "skipping block {}, it is not at the start of an epoch",
+                header.block_number
+            );
+            continue;
+        }
+
+        let Some(EpochResult { epoch, result }) = validator.push(header)? else {
+            continue;
+        };
+
+        let line = match result {
+            Ok(EpochStatus::AlreadySynced) => format!("epoch {}: already synced", epoch),
+            Ok(EpochStatus::Validated) => format!("epoch {}: valid", epoch),
+            Err(e) => {
+                all_valid = false;
+                format!("epoch {}: invalid: {}", epoch, e)
+            }
+        };
+        writeln!(writer, "{}", line).map_err(|_| EraValidateError::IoError)?;
+    }
+
+    if let Err(EraValidateError::PartialEpoch(epoch, headers)) = validator.finish() {
+        if end_block.is_some() {
+            all_valid = false;
+        }
+        writeln!(
+            writer,
+            "epoch {}: incomplete: {} of {} headers",
+            epoch,
+            headers,
+            pre_merge_epoch_size(epoch)
+        )
+        .map_err(|_| EraValidateError::IoError)?;
+    }
+
+    Ok(all_valid)
+}
+
+/// Reads the next [`Block`] from a stream of one or more concatenated dbin files.
+///
+/// Returns `Ok(None)` once the stream ends on a message boundary.
+pub fn read_block<R: Read>(reader: &mut R) -> Result<Option<Block>, EraValidateError> {
+    let mut size = [0u8; 4];
+    if !read_exact_or_eof(reader, &mut size)? {
+        return Ok(None);
+    }
+
+    // every dbin file starts with a header: the magic bytes, a version byte,
+    // 3 bytes of content type and 2 bytes of content version
+    if &size == b"dbin" {
+        let mut header = [0u8; 6];
+        reader
+            .read_exact(&mut header)
+            .map_err(|_| EraValidateError::FlatFileDecodeError)?;
+        if header[0] != 0 {
+            return Err(EraValidateError::FlatFileDecodeError);
+        }
+        if !read_exact_or_eof(reader, &mut size)? {
+            return Ok(None);
+        }
+    }
+
+    let mut message = vec![0u8; u32::from_be_bytes(size) as usize];
+    reader
+        .read_exact(&mut message)
+        .map_err(|_| EraValidateError::FlatFileDecodeError)?;
+
+    let bstream_block = BstreamBlock::decode(message.as_slice())
+        .map_err(|_| EraValidateError::FlatFileDecodeError)?;
+    let block = Block::decode(bstream_block.payload_buffer.as_slice())
+        .map_err(|_| EraValidateError::FlatFileDecodeError)?;
+
+    Ok(Some(block))
+}
+
+/// Fills `buf` from `reader`, returning `Ok(false)` if the stream ended before any byte was read.
+fn read_exact_or_eof<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<bool, EraValidateError> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]) {
+            Ok(0) if read == 0 => return Ok(false),
+            Ok(0) => return Err(EraValidateError::FlatFileDecodeError),
+            Ok(n) => read += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(_) => return Err(EraValidateError::IoError),
+        }
+    }
+    Ok(true)
+}
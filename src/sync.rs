@@ -1,16 +1,30 @@
+use alloy_primitives::{keccak256, B256};
 use base64::prelude::*;
+use fs2::FileExt;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::error::Error;
-use std::fs::{metadata, OpenOptions};
-use std::io::{Read, Write};
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tempfile::NamedTempFile;
 
 use crate::errors::{EraValidateError, HeaderAccumulatorError, SyncError};
+use crate::types::ExtHeaderRecord;
+
+/// The lockfile schema version written by this crate.
+///
+/// Version 1 lockfiles only stored the root of each epoch, they are migrated when read.
+pub const LOCKFILE_VERSION: u32 = 2;
 
 pub struct LockEntry {
     epoch: String,
     root: String,
+    provenance: Option<Provenance>,
 }
 
 impl LockEntry {
@@ -18,13 +32,197 @@ impl LockEntry {
         LockEntry {
             epoch: epoch.to_string(),
             root: BASE64_STANDARD.encode(root),
+            provenance: None,
+        }
+    }
+
+    pub fn with_provenance(mut self, provenance: Provenance) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+}
+
+impl From<&EpochRecord> for LockEntry {
+    fn from(record: &EpochRecord) -> Self {
+        LockEntry {
+            provenance: record.provenance.clone(),
+            ..LockEntry::new(&record.epoch, record.root)
+        }
+    }
+}
+
+/// How and when an epoch was validated
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Identifier of the pre-merge accumulator the epoch was validated against,
+    /// see [`crate::types::pre_merge_accumulator_id`]
+    pub accumulator: B256,
+    /// Seconds since the Unix epoch
+    pub validated_at: u64,
+    /// Version of this crate that validated the epoch
+    pub tool_version: String,
+    /// Where the headers were read from, e.g. flat file names
+    pub sources: Vec<String>,
+    /// keccak256 digest of the validated header records, see [`header_records_digest`]
+    pub source_digest: B256,
+    /// Number of headers that were validated
+    pub block_count: usize,
+}
+
+impl Provenance {
+    /// Describes the validation of `headers` against the accumulator identified by
+    /// `accumulator`, happening now.
+    pub fn new(accumulator: B256, sources: Vec<String>, headers: &[ExtHeaderRecord]) -> Self {
+        Provenance {
+            accumulator,
+            validated_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            sources,
+            source_digest: header_records_digest(headers),
+            block_count: headers.len(),
+        }
+    }
+}
+
+/// Returns the keccak256 digest of the concatenated block hashes and big endian total
+/// difficulties of `headers`.
+pub fn header_records_digest(headers: &[ExtHeaderRecord]) -> B256 {
+    let mut bytes = Vec::with_capacity(headers.len() * 64);
+    for header in headers {
+        bytes.extend_from_slice(header.block_hash.as_slice());
+        bytes.extend_from_slice(&header.total_difficulty.to_be_bytes::<32>());
+    }
+    keccak256(bytes)
+}
+
+/// An epoch that was validated, as recorded by a [`SyncStore`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EpochRecord {
+    pub epoch: usize,
+    pub root: [u8; 32],
+    /// `None` for epochs recorded before provenance was tracked
+    pub provenance: Option<Provenance>,
+}
+
+impl EpochRecord {
+    pub fn new(epoch: usize, root: [u8; 32]) -> Self {
+        EpochRecord {
+            epoch,
+            root,
+            provenance: None,
         }
     }
+
+    pub fn with_provenance(mut self, provenance: Provenance) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+}
+
+/// Keeps track of the epochs that were already validated, so they can be skipped.
+///
+/// Implementations take `&self` and handle their own synchronization, so a store can be
+/// shared between validators.
+pub trait SyncStore: fmt::Debug + Send + Sync {
+    fn get(&self, epoch: usize) -> Result<Option<EpochRecord>, SyncError>;
+
+    /// Records `record`, replacing any previous record for the same epoch
+    fn put(&self, record: EpochRecord) -> Result<(), SyncError>;
+
+    /// Returns every record, ordered by epoch
+    fn list(&self) -> Result<Vec<EpochRecord>, SyncError>;
+
+    /// Removes the record of `epoch`, returning whether there was one
+    fn delete(&self, epoch: usize) -> Result<bool, SyncError>;
+
+    /// Checks if `epoch` was already synced.
+    ///
+    /// Returns an error if the stored root differs from `premerge_accumulator_hash`.
+    fn check_sync_state(
+        &self,
+        epoch: usize,
+        premerge_accumulator_hash: [u8; 32],
+    ) -> Result<bool, HeaderAccumulatorError> {
+        check_epoch_record(self.get(epoch)?, premerge_accumulator_hash)
+    }
 }
 
-#[derive(Default, Serialize, Deserialize)]
+/// Returns whether an epoch whose stored record is `record` was already synced, see
+/// [`SyncStore::check_sync_state`]
+fn check_epoch_record(
+    record: Option<EpochRecord>,
+    premerge_accumulator_hash: [u8; 32],
+) -> Result<bool, HeaderAccumulatorError> {
+    let Some(record) = record else {
+        return Ok(false);
+    };
+
+    if premerge_accumulator_hash != record.root {
+        log::error!(
+            "the valid hash is: {:?} and the provided hash was: {:?}",
+            premerge_accumulator_hash,
+            record.root
+        );
+        return Err(EraValidateError::EraAccumulatorMismatch.into());
+    }
+
+    Ok(true)
+}
+
+/// A lockfile entry, as stored
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "StoredEntryRepr")]
+struct StoredEntry {
+    root: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    provenance: Option<Provenance>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredEntryRepr {
+    /// Version 1 lockfiles only stored the root
+    Legacy(String),
+    Current {
+        root: String,
+        #[serde(default)]
+        provenance: Option<Provenance>,
+    },
+}
+
+impl From<StoredEntryRepr> for StoredEntry {
+    fn from(repr: StoredEntryRepr) -> Self {
+        match repr {
+            StoredEntryRepr::Legacy(root) => StoredEntry {
+                root,
+                provenance: None,
+            },
+            StoredEntryRepr::Current { root, provenance } => StoredEntry { root, provenance },
+        }
+    }
+}
+
+fn legacy_lockfile_version() -> u32 {
+    1
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct Lock {
-    entries: HashMap<String, String>,
+    #[serde(default = "legacy_lockfile_version")]
+    version: u32,
+    entries: HashMap<String, StoredEntry>,
+}
+
+impl Default for Lock {
+    fn default() -> Self {
+        Lock {
+            version: LOCKFILE_VERSION,
+            entries: HashMap::new(),
+        }
+    }
 }
 
 impl Lock {
@@ -32,98 +230,464 @@ impl Lock {
         Lock::default()
     }
 
+    /// Checks if `epoch` was already synced, like [`SyncStore::check_sync_state`]
     pub fn check_sync_state(
         &self,
-        file_path: &Path,
         epoch: usize,
         premerge_accumulator_hash: [u8; 32],
     ) -> Result<bool, HeaderAccumulatorError> {
-        if metadata(file_path).is_err() {
-            log::info!("The lockfile did not exist and was created");
-        }
+        check_epoch_record(self.get(epoch)?, premerge_accumulator_hash)
+    }
 
-        let epoch = epoch.to_string();
+    /// Checks every entry of the lock, returning all the invalid ones ordered by epoch
+    /// instead of stopping at the first.
+    pub fn verify_integrity(&self) -> Result<(), Vec<SyncError>> {
+        let (_, errors) = self.partition_records();
 
-        if !self.entries.contains_key(&epoch) {
-            return Ok(false);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
         }
+    }
 
-        let stored_hash = self
-            .entries
-            .get(&epoch)
-            .ok_or(SyncError::LockfileReadError)?;
+    /// Decodes every entry of the lock, returning the valid records and the errors of the
+    /// invalid entries, both ordered by epoch.
+    pub fn partition_records(&self) -> (Vec<EpochRecord>, Vec<SyncError>) {
+        let mut keys: Vec<&String> = self.entries.keys().collect();
+        keys.sort_by_key(|key| (key.parse::<usize>().unwrap_or(usize::MAX), key.as_str()));
 
-        let stored_hash = BASE64_STANDARD
-            .decode(stored_hash)
-            .expect("Failed to decode Base64");
+        let mut records = Vec::new();
+        let mut errors = Vec::new();
+        for key in keys {
+            match self.entries[key].to_record_with_key(key) {
+                Ok(record) => records.push(record),
+                Err(e) => errors.push(e),
+            }
+        }
+        (records, errors)
+    }
 
-        // this ensures the decoded bytes fit into a `[u8; 32]` array, which is the hash type
-        let stored_hash: [u8; 32] = match stored_hash.try_into() {
-            Ok(b) => b,
-            Err(_) => panic!("Decoded hash does not fit into a 32-byte array"),
+    /// Reads the lock stored at `file_path`, which is an empty lock if there is no lockfile.
+    ///
+    /// A lockfile that cannot be parsed is an error rather than an empty lock, so a corrupt
+    /// lockfile never silently discards the sync state.
+    pub fn from_file(file_path: &Path) -> Result<Self, SyncError> {
+        let contents = match fs::read_to_string(file_path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Lock::new()),
+            Err(e) => return Err(e.into()),
         };
 
-        if premerge_accumulator_hash != stored_hash {
-            log::error!(
-                "the valid hash is: {:?} and the provided hash was: {:?}",
-                premerge_accumulator_hash,
-                stored_hash
+        if contents.trim().is_empty() {
+            return Ok(Lock::new());
+        }
+
+        let mut lock: Lock = serde_json::from_str(&contents)?;
+        if lock.version > LOCKFILE_VERSION {
+            return Err(SyncError::UnsupportedLockfileVersion(lock.version));
+        }
+        if lock.version < LOCKFILE_VERSION {
+            log::info!(
+                "migrating lockfile from version {} to {}",
+                lock.version,
+                LOCKFILE_VERSION
             );
-            return Err(EraValidateError::EraAccumulatorMismatch.into());
+            lock.version = LOCKFILE_VERSION;
         }
 
-        Ok(true)
+        Ok(lock)
     }
 
-    pub fn from_file(file_path: &Path) -> Result<Self, SyncError> {
-        let mut file = OpenOptions::new()
-            .read(true)
+    /// Writes the lock to `file_path`.
+    ///
+    /// The lock is written to a uniquely named temporary file next to `file_path` which then
+    /// replaces it, so a crash mid-write leaves the previous state intact.
+    pub fn store_last_state(&self, file_path: &Path) -> Result<(), SyncError> {
+        let json_string = self.to_json()?;
+        let directory = match file_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+
+        // the temporary file is removed if it is dropped before being persisted
+        let mut file = NamedTempFile::new_in(directory)?;
+        file.write_all(json_string.as_bytes())?;
+        file.as_file().sync_all()?;
+        file.persist(file_path).map_err(|e| e.error)?;
+
+        // the rename itself is only durable once the directory holding it is synced
+        #[cfg(unix)]
+        File::open(directory)?.sync_all()?;
+
+        Ok(())
+    }
+
+    /// Reads the lock stored at `file_path`, applies `update` to it and stores it back.
+    ///
+    /// An exclusive advisory lock is held on a `.lock` file next to `file_path` for the
+    /// whole read-modify-write, so concurrent validators sharing a lockfile do not overwrite
+    /// each other's updates.
+    pub fn modify<T>(
+        file_path: &Path,
+        update: impl FnOnce(&mut Lock) -> T,
+    ) -> Result<T, SyncError> {
+        let guard = OpenOptions::new()
             .write(true)
             .create(true)
             .truncate(false)
-            .open(file_path)?;
+            .open(sibling_path(file_path, ".lock"))?;
+        guard.lock_exclusive()?;
 
-        let mut contents = String::new();
+        let mut lock = Lock::from_file(file_path)?;
+        let result = update(&mut lock);
+        lock.store_last_state(file_path)?;
 
-        file.read_to_string(&mut contents)?;
+        FileExt::unlock(&guard)?;
+        Ok(result)
+    }
 
-        Ok(if contents.trim().is_empty() {
-            Lock::new()
-        } else {
-            serde_json::from_str(&contents).unwrap_or_default()
+    fn to_json(&self) -> Result<String, SyncError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn update(&mut self, entry: LockEntry) {
+        self.entries.insert(
+            entry.epoch,
+            StoredEntry {
+                root: entry.root,
+                provenance: entry.provenance,
+            },
+        );
+    }
+
+    /// Removes the entry of `epoch`, returning whether there was one
+    pub fn remove(&mut self, epoch: usize) -> bool {
+        self.entries.remove(&epoch.to_string()).is_some()
+    }
+
+    /// Returns the record of `epoch`, if the lock holds one
+    pub fn get(&self, epoch: usize) -> Result<Option<EpochRecord>, SyncError> {
+        self.entries
+            .get(&epoch.to_string())
+            .map(|entry| entry.to_record(epoch))
+            .transpose()
+    }
+
+    /// Returns every record in the lock, ordered by epoch
+    pub fn records(&self) -> Result<Vec<EpochRecord>, SyncError> {
+        let mut records = self
+            .entries
+            .iter()
+            .map(|(key, entry)| entry.to_record_with_key(key))
+            .collect::<Result<Vec<_>, SyncError>>()?;
+        records.sort_by_key(|record| record.epoch);
+        Ok(records)
+    }
+
+    /// Adds the entries of `other` to this lock.
+    ///
+    /// Epochs recorded by both locks must have the same root, otherwise nothing is merged and
+    /// every conflicting epoch is returned in [`SyncError::ConflictingRoots`]. For epochs
+    /// recorded by both, the provenance of this lock is kept if it has one.
+    pub fn merge(&mut self, other: &Lock) -> Result<(), SyncError> {
+        let diff = self.diff(other)?;
+        if !diff.conflicting.is_empty() {
+            return Err(SyncError::ConflictingRoots(diff.conflicting));
+        }
+
+        for record in other.records()? {
+            let entry = self.entries.get(&record.epoch.to_string());
+            if entry.is_some_and(|entry| entry.provenance.is_some() || record.provenance.is_none())
+            {
+                continue;
+            }
+            self.update(LockEntry::from(&record));
+        }
+        Ok(())
+    }
+
+    /// Compares the epochs recorded by this lock with the ones recorded by `other`
+    pub fn diff(&self, other: &Lock) -> Result<LockDiff, SyncError> {
+        let ours: BTreeMap<usize, [u8; 32]> = self
+            .records()?
+            .into_iter()
+            .map(|record| (record.epoch, record.root))
+            .collect();
+        let theirs: BTreeMap<usize, [u8; 32]> = other
+            .records()?
+            .into_iter()
+            .map(|record| (record.epoch, record.root))
+            .collect();
+
+        let mut diff = LockDiff::default();
+        for (&epoch, &ours_root) in &ours {
+            match theirs.get(&epoch) {
+                None => diff.only_ours.push(epoch),
+                Some(&theirs_root) if theirs_root == ours_root => diff.common.push(epoch),
+                Some(&theirs_root) => diff.conflicting.push(RootConflict {
+                    epoch,
+                    ours: ours_root,
+                    theirs: theirs_root,
+                }),
+            }
+        }
+        diff.only_theirs = theirs
+            .keys()
+            .filter(|epoch| !ours.contains_key(epoch))
+            .copied()
+            .collect();
+
+        Ok(diff)
+    }
+}
+
+/// Two different roots recorded for the same epoch
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RootConflict {
+    pub epoch: usize,
+    pub ours: [u8; 32],
+    pub theirs: [u8; 32],
+}
+
+/// The difference between two locks, see [`Lock::diff`]. Every list is ordered by epoch.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LockDiff {
+    /// Epochs only recorded by the first lock
+    pub only_ours: Vec<usize>,
+    /// Epochs only recorded by the second lock
+    pub only_theirs: Vec<usize>,
+    /// Epochs recorded with the same root by both locks
+    pub common: Vec<usize>,
+    /// Epochs recorded with different roots
+    pub conflicting: Vec<RootConflict>,
+}
+
+impl LockDiff {
+    /// Returns `true` if both locks record the same epochs with the same roots
+    pub fn is_empty(&self) -> bool {
+        self.only_ours.is_empty() && self.only_theirs.is_empty() && self.conflicting.is_empty()
+    }
+}
+
+/// Returns `path` with `suffix` appended to its file name
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = OsString::from(path);
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+impl StoredEntry {
+    fn to_record_with_key(&self, key: &str) -> Result<EpochRecord, SyncError> {
+        let epoch = key
+            .parse::<usize>()
+            .map_err(|_| SyncError::SchemaMismatch {
+                epoch: key.to_string(),
+                value: self.root.clone(),
+            })?;
+        self.to_record(epoch)
+    }
+
+    fn to_record(&self, epoch: usize) -> Result<EpochRecord, SyncError> {
+        let root = BASE64_STANDARD
+            .decode(&self.root)
+            .map_err(|_| SyncError::InvalidEncoding {
+                epoch: epoch.to_string(),
+                value: self.root.clone(),
+            })?;
+        let root = root
+            .try_into()
+            .map_err(|root: Vec<u8>| SyncError::WrongLength {
+                epoch: epoch.to_string(),
+                value: self.root.clone(),
+                length: root.len(),
+            })?;
+
+        Ok(EpochRecord {
+            epoch,
+            root,
+            provenance: self.provenance.clone(),
         })
     }
+}
 
-    pub fn store_last_state(&self, file_path: &Path) -> Result<(), Box<dyn Error>> {
-        let json_string = self.to_json()?;
+/// A [`SyncStore`] backed by a JSON lockfile, read and rewritten in full on every update
+#[derive(Clone, Debug)]
+pub struct JsonSyncStore {
+    path: PathBuf,
+}
 
-        let mut file = OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .open(file_path)?;
+impl JsonSyncStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        JsonSyncStore { path: path.into() }
+    }
 
-        file.write_all(json_string.as_bytes())?;
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
 
+impl SyncStore for JsonSyncStore {
+    fn get(&self, epoch: usize) -> Result<Option<EpochRecord>, SyncError> {
+        Lock::from_file(&self.path)?.get(epoch)
+    }
+
+    fn put(&self, record: EpochRecord) -> Result<(), SyncError> {
+        Lock::modify(&self.path, |lock| lock.update(LockEntry::from(&record)))
+    }
+
+    fn list(&self) -> Result<Vec<EpochRecord>, SyncError> {
+        Lock::from_file(&self.path)?.records()
+    }
+
+    fn delete(&self, epoch: usize) -> Result<bool, SyncError> {
+        Lock::modify(&self.path, |lock| lock.remove(epoch))
+    }
+}
+
+/// A [`SyncStore`] backed by an embedded SQLite database, with one row per epoch
+#[derive(Debug)]
+pub struct SqliteSyncStore {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteSyncStore {
+    /// Opens the database at `path`, creating it if it does not exist
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SyncError> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// Opens a database that only lives in memory, mostly useful for tests
+    pub fn open_in_memory() -> Result<Self, SyncError> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(mut connection: Connection) -> Result<Self, SyncError> {
+        // the whole migration is one immediate transaction, so concurrent openers of a new
+        // database wait for each other and a crash never leaves it half migrated
+        let transaction = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        transaction.execute(
+            "CREATE TABLE IF NOT EXISTS epochs (
+                epoch INTEGER PRIMARY KEY,
+                root BLOB NOT NULL
+            )",
+            [],
+        )?;
+
+        // databases before version 1 only stored the root of each epoch, and new databases are
+        // created in that layout, so both gain the provenance column here
+        let version: i64 = transaction.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version < 1 {
+            transaction.execute_batch(
+                "ALTER TABLE epochs ADD COLUMN provenance TEXT;
+                PRAGMA user_version = 1;",
+            )?;
+        }
+        transaction.commit()?;
+
+        Ok(SqliteSyncStore {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn connection(&self) -> std::sync::MutexGuard<'_, Connection> {
+        // a panic while holding the lock cannot leave a transaction half applied
+        self.connection
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn record_from_row(
+    epoch: i64,
+    root: Vec<u8>,
+    provenance: Option<String>,
+) -> Result<EpochRecord, SyncError> {
+    let root = root
+        .try_into()
+        .map_err(|root: Vec<u8>| SyncError::WrongLength {
+            epoch: epoch.to_string(),
+            value: BASE64_STANDARD.encode(&root),
+            length: root.len(),
+        })?;
+    let provenance = provenance
+        .map(|provenance| {
+            serde_json::from_str(&provenance).map_err(|_| SyncError::SchemaMismatch {
+                epoch: epoch.to_string(),
+                value: provenance,
+            })
+        })
+        .transpose()?;
+
+    Ok(EpochRecord {
+        epoch: epoch as usize,
+        root,
+        provenance,
+    })
+}
+
+impl SyncStore for SqliteSyncStore {
+    fn get(&self, epoch: usize) -> Result<Option<EpochRecord>, SyncError> {
+        let row: Option<(Vec<u8>, Option<String>)> = self
+            .connection()
+            .query_row(
+                "SELECT root, provenance FROM epochs WHERE epoch = ?1",
+                params![epoch as i64],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        row.map(|(root, provenance)| record_from_row(epoch as i64, root, provenance))
+            .transpose()
+    }
+
+    fn put(&self, record: EpochRecord) -> Result<(), SyncError> {
+        let provenance = record
+            .provenance
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
+
+        self.connection().execute(
+            "INSERT OR REPLACE INTO epochs (epoch, root, provenance) VALUES (?1, ?2, ?3)",
+            params![record.epoch as i64, record.root.as_slice(), provenance],
+        )?;
         Ok(())
     }
 
-    fn to_json(&self) -> Result<String, Box<dyn Error>> {
-        Ok(serde_json::to_string_pretty(self)?)
+    fn list(&self) -> Result<Vec<EpochRecord>, SyncError> {
+        let connection = self.connection();
+        let mut statement =
+            connection.prepare("SELECT epoch, root, provenance FROM epochs ORDER BY epoch")?;
+        let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+
+        let mut records = Vec::new();
+        for row in rows {
+            let (epoch, root, provenance) = row?;
+            records.push(record_from_row(epoch, root, provenance)?);
+        }
+        Ok(records)
     }
 
-    pub fn update(&mut self, entry: LockEntry) {
-        self.entries.insert(entry.epoch, entry.root);
+    fn delete(&self, epoch: usize) -> Result<bool, SyncError> {
+        let deleted = self
+            .connection()
+            .execute("DELETE FROM epochs WHERE epoch = ?1", params![epoch as i64])?;
+        Ok(deleted > 0)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs::File;
-    use std::io::Write;
+    use std::error::Error;
+    use std::io::Read;
+    use std::sync::Barrier;
     use tempfile::tempdir;
     use trin_validation::accumulator::PreMergeAccumulator;
 
+    use crate::types::pre_merge_accumulator_id;
+
     #[test]
     fn test_store_last_state() -> Result<(), Box<dyn Error>> {
         let dir = tempdir()?;
@@ -132,11 +696,20 @@ mod tests {
         let entry = LockEntry {
             epoch: "0".into(),
             root: "XsH/uMOxRvQmBsdM7Zc9wW7FoQfANFhYw0P8lHgLQhg=".into(),
+            provenance: None,
         };
 
+        // reading a missing lockfile does not create it
         let mut lock_file = Lock::from_file(&file_path).unwrap();
+        assert!(lock_file.entries.is_empty());
+        assert!(!file_path.exists());
+
         lock_file.update(entry);
         lock_file.store_last_state(&file_path)?;
+        lock_file.store_last_state(&file_path)?;
+
+        // only the lockfile is left once its temporary files are persisted
+        assert_eq!(fs::read_dir(dir.path())?.count(), 1);
 
         let mut file = File::open(file_path)?;
         let mut contents = String::new();
@@ -147,8 +720,8 @@ mod tests {
         // test if the entry was correctly added
         assert!(lock.entries.contains_key("0"));
         assert_eq!(
-            lock.entries.get("0"),
-            Some(&"XsH/uMOxRvQmBsdM7Zc9wW7FoQfANFhYw0P8lHgLQhg=".into())
+            lock.entries.get("0").map(|entry| entry.root.as_str()),
+            Some("XsH/uMOxRvQmBsdM7Zc9wW7FoQfANFhYw0P8lHgLQhg=")
         );
 
         Ok(())
@@ -175,24 +748,21 @@ mod tests {
 
         // Test case where epoch exists and hashes match
         let epoch = 0;
-        assert_eq!(
-            json_lock
-                .check_sync_state(&file_path, epoch, mac_file.historical_epochs[0].0)
-                .unwrap(),
-            true
-        );
+        assert!(json_lock
+            .check_sync_state(epoch, mac_file.historical_epochs[0].0)
+            .unwrap());
 
         // Test case where epoch does not exist
         let epoch = 2;
         let result = json_lock
-            .check_sync_state(&file_path, epoch.clone(), mac_file.historical_epochs[2].0)
+            .check_sync_state(epoch, mac_file.historical_epochs[2].0)
             .unwrap();
-        assert_eq!(result, false);
+        assert!(!result);
 
         // // test when hashes differ but lock is present
         let epoch = 0;
         let result = json_lock
-            .check_sync_state(&file_path, epoch.clone(), mac_file.historical_epochs[1].0)
+            .check_sync_state(epoch, mac_file.historical_epochs[1].0)
             .map_err(|error| error.to_string());
         assert_eq!(
             result.unwrap_err(),
@@ -202,9 +772,320 @@ mod tests {
         // test case for another epoch hash
         let epoch = 1;
         let result = json_lock
-            .check_sync_state(&file_path, epoch.clone(), mac_file.historical_epochs[1].0)
+            .check_sync_state(epoch, mac_file.historical_epochs[1].0)
             .map_err(|error| error.to_string());
-        assert_eq!(result.unwrap(), true);
+        assert!(result.unwrap());
+
+        Ok(())
+    }
+
+    fn check_store(store: &dyn SyncStore) -> Result<(), SyncError> {
+        let mac_file = PreMergeAccumulator::default();
+        let provenance = Provenance::new(
+            pre_merge_accumulator_id(&mac_file),
+            vec!["0000000000.dbin".into()],
+            &[],
+        );
+        let record = |epoch: usize| {
+            EpochRecord::new(epoch, mac_file.historical_epochs[epoch].0)
+                .with_provenance(provenance.clone())
+        };
+
+        assert_eq!(store.get(0)?, None);
+        assert!(store.list()?.is_empty());
+
+        store.put(record(2))?;
+        store.put(record(0))?;
+        assert_eq!(store.get(0)?, Some(record(0)));
+        assert_eq!(store.list()?, vec![record(0), record(2)]);
+
+        // putting an epoch again replaces its record
+        store.put(EpochRecord::new(2, [0; 32]))?;
+        assert_eq!(store.get(2)?, Some(EpochRecord::new(2, [0; 32])));
+
+        assert!(store
+            .check_sync_state(0, mac_file.historical_epochs[0].0)
+            .unwrap());
+        assert!(!store
+            .check_sync_state(1, mac_file.historical_epochs[1].0)
+            .unwrap());
+        assert!(store
+            .check_sync_state(2, mac_file.historical_epochs[2].0)
+            .is_err());
+
+        assert!(store.delete(2)?);
+        assert!(!store.delete(2)?);
+        assert_eq!(store.list()?, vec![record(0)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_sync_store() -> Result<(), Box<dyn Error>> {
+        let dir = tempdir()?;
+        let store = JsonSyncStore::new(dir.path().join("lockfile.json"));
+        check_store(&store)?;
+
+        // the records are kept in the lockfile format
+        let lock = Lock::from_file(store.path())?;
+        assert_eq!(lock.entries.len(), 1);
+        assert!(lock.entries.contains_key("0"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sqlite_sync_store() -> Result<(), Box<dyn Error>> {
+        check_store(&SqliteSyncStore::open_in_memory()?)?;
+
+        // records survive reopening the database
+        let dir = tempdir()?;
+        let path = dir.path().join("sync.sqlite");
+        SqliteSyncStore::open(&path)?.put(EpochRecord::new(7, [7; 32]))?;
+        assert_eq!(
+            SqliteSyncStore::open(&path)?.list()?,
+            vec![EpochRecord::new(7, [7; 32])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupt_lockfile() -> Result<(), Box<dyn Error>> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("lockfile.json");
+        fs::write(&file_path, r#"{ "entries": { "0": "#)?;
+
+        assert!(matches!(
+            Lock::from_file(&file_path),
+            Err(SyncError::LockfileJsonError(_))
+        ));
+        assert!(JsonSyncStore::new(&file_path)
+            .put(EpochRecord::new(1, [1; 32]))
+            .is_err());
+
+        // the corrupt lockfile is left untouched for inspection
+        assert_eq!(fs::read_to_string(&file_path)?, r#"{ "entries": { "0": "#);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_entries() -> Result<(), Box<dyn Error>> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("lockfile.json");
+        let valid = BASE64_STANDARD.encode([0; 32]);
+        let short = BASE64_STANDARD.encode([0; 31]);
+        fs::write(
+            &file_path,
+            format!(
+                r#"{{ "version": 2, "entries": {{
+                    "0": {{ "root": "{valid}" }},
+                    "1": {{ "root": "not base64!" }},
+                    "2": {{ "root": "{short}" }},
+                    "three": {{ "root": "{valid}" }}
+                }} }}"#
+            ),
+        )?;
+        let lock = Lock::from_file(&file_path)?;
+
+        assert!(lock.check_sync_state(0, [0; 32])?);
+        assert!(matches!(
+            lock.check_sync_state(1, [0; 32]),
+            Err(HeaderAccumulatorError::SyncError(SyncError::InvalidEncoding { epoch, value }))
+                if epoch == "1" && value == "not base64!"
+        ));
+        assert!(matches!(
+            lock.check_sync_state(2, [0; 32]),
+            Err(HeaderAccumulatorError::SyncError(SyncError::WrongLength { epoch, length: 31, .. }))
+                if epoch == "2"
+        ));
+
+        let errors = lock.verify_integrity().unwrap_err();
+        assert_eq!(errors.len(), 3);
+        assert!(matches!(&errors[0], SyncError::InvalidEncoding { epoch, .. } if epoch == "1"));
+        assert!(matches!(&errors[1], SyncError::WrongLength { epoch, .. } if epoch == "2"));
+        assert!(matches!(&errors[2], SyncError::SchemaMismatch { epoch, .. } if epoch == "three"));
+
+        let mut lock = Lock::new();
+        lock.update(LockEntry::new(&0, [0; 32]));
+        assert!(lock.verify_integrity().is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_and_diff() -> Result<(), Box<dyn Error>> {
+        let provenance = Provenance::new(B256::ZERO, vec!["0.dbin".into()], &[]);
+
+        let mut ours = Lock::new();
+        ours.update(LockEntry::new(&0, [0; 32]));
+        ours.update(LockEntry::new(&1, [1; 32]));
+        let mut theirs = Lock::new();
+        theirs.update(LockEntry::new(&1, [1; 32]).with_provenance(provenance.clone()));
+        theirs.update(LockEntry::new(&2, [2; 32]));
+
+        let diff = ours.diff(&theirs)?;
+        assert_eq!(diff.only_ours, vec![0]);
+        assert_eq!(diff.only_theirs, vec![2]);
+        assert_eq!(diff.common, vec![1]);
+        assert!(diff.conflicting.is_empty());
+        assert!(!diff.is_empty());
+
+        ours.merge(&theirs)?;
+        assert_eq!(ours.records()?.len(), 3);
+        assert_eq!(ours.get(1)?.unwrap().provenance, Some(provenance));
+        assert!(ours.diff(&ours)?.is_empty());
+
+        let mut conflicting = Lock::new();
+        conflicting.update(LockEntry::new(&2, [3; 32]));
+        conflicting.update(LockEntry::new(&3, [3; 32]));
+
+        let error = ours.merge(&conflicting).unwrap_err();
+        assert!(matches!(
+            &error,
+            SyncError::ConflictingRoots(conflicts) if conflicts == &vec![RootConflict {
+                epoch: 2,
+                ours: [2; 32],
+                theirs: [3; 32],
+            }]
+        ));
+        // a conflicting merge leaves the lock untouched
+        assert_eq!(ours.get(3)?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_store_last_state_is_atomic() -> Result<(), Box<dyn Error>> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("lockfile.json");
+
+        let mut lock = Lock::new();
+        lock.update(LockEntry::new(&0, [0; 32]));
+        lock.store_last_state(&file_path)?;
+        lock.update(LockEntry::new(&1, [1; 32]));
+        lock.store_last_state(&file_path)?;
+
+        // only the lockfile remains, no temporary file is left behind
+        let files: Vec<_> = fs::read_dir(dir.path())?.collect::<Result<_, _>>()?;
+        assert_eq!(files.len(), 1);
+        assert_eq!(Lock::from_file(&file_path)?.entries.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_updates() -> Result<(), Box<dyn Error>> {
+        let dir = tempdir()?;
+        let store = JsonSyncStore::new(dir.path().join("lockfile.json"));
+
+        std::thread::scope(|scope| {
+            for worker in 0..8 {
+                let store = &store;
+                scope.spawn(move || {
+                    for i in 0..10 {
+                        let epoch = worker * 10 + i;
+                        store
+                            .put(EpochRecord::new(epoch, [epoch as u8; 32]))
+                            .unwrap();
+                    }
+                });
+            }
+        });
+
+        let records = store.list()?;
+        assert_eq!(records.len(), 80);
+        assert!(records
+            .iter()
+            .all(|record| record.root == [record.epoch as u8; 32]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lockfile_migration() -> Result<(), Box<dyn Error>> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("lockfile.json");
+        fs::write(
+            &file_path,
+            r#"{ "entries": { "0": "XsH/uMOxRvQmBsdM7Zc9wW7FoQfANFhYw0P8lHgLQhg=" } }"#,
+        )?;
+
+        let store = JsonSyncStore::new(&file_path);
+        let legacy = store.get(0)?.unwrap();
+        assert_eq!(legacy.provenance, None);
+
+        let provenance = Provenance::new(B256::repeat_byte(1), Vec::new(), &[]);
+        store.put(EpochRecord::new(1, [1; 32]).with_provenance(provenance.clone()))?;
+
+        // the lockfile is rewritten in the current schema, keeping the legacy entry
+        let contents: serde_json::Value = serde_json::from_str(&fs::read_to_string(&file_path)?)?;
+        assert_eq!(contents["version"], LOCKFILE_VERSION);
+        assert_eq!(
+            contents["entries"]["0"]["root"],
+            "XsH/uMOxRvQmBsdM7Zc9wW7FoQfANFhYw0P8lHgLQhg="
+        );
+        assert_eq!(store.get(0)?, Some(legacy));
+        assert_eq!(store.get(1)?.unwrap().provenance, Some(provenance));
+
+        // lockfiles from a newer version are not overwritten
+        fs::write(&file_path, r#"{ "version": 3, "entries": {} }"#)?;
+        assert!(matches!(
+            Lock::from_file(&file_path),
+            Err(SyncError::UnsupportedLockfileVersion(3))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sqlite_migration() -> Result<(), Box<dyn Error>> {
+        let dir = tempdir()?;
+        let path = dir.path().join("sync.sqlite");
+
+        let connection = Connection::open(&path)?;
+        connection.execute_batch(
+            "CREATE TABLE epochs (epoch INTEGER PRIMARY KEY, root BLOB NOT NULL);
+            INSERT INTO epochs (epoch, root) VALUES (0, zeroblob(32));",
+        )?;
+        drop(connection);
+
+        let store = SqliteSyncStore::open(&path)?;
+        assert_eq!(store.list()?, vec![EpochRecord::new(0, [0; 32])]);
+
+        let provenance = Provenance::new(B256::repeat_byte(1), Vec::new(), &[]);
+        store.put(EpochRecord::new(1, [1; 32]).with_provenance(provenance.clone()))?;
+        assert_eq!(store.get(1)?.unwrap().provenance, Some(provenance));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sqlite_concurrent_migration() -> Result<(), Box<dyn Error>> {
+        let dir = tempdir()?;
+        let path = dir.path().join("sync.sqlite");
+
+        // every opener of a new database migrates it, only the first one alters it
+        let openers = 16;
+        let barrier = Barrier::new(openers);
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..openers)
+                .map(|_| {
+                    scope.spawn(|| {
+                        barrier.wait();
+                        SqliteSyncStore::open(&path).map(|_| ())
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .try_for_each(|handle| handle.join().unwrap())
+        })?;
+
+        let connection = Connection::open(&path)?;
+        let version: i64 = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        assert_eq!(version, 1);
 
         Ok(())
     }
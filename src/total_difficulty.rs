@@ -0,0 +1,119 @@
+use alloy_primitives::U256;
+use ethportal_api::Header;
+use sf_protos::ethereum::r#type::v2::Block;
+
+use crate::{errors::EraValidateError, types::ExtHeaderRecord};
+
+/// Derives the total difficulty of consecutive headers by summing their difficulty, starting
+/// from a known checkpoint.
+///
+/// This makes it possible to validate epochs from sources that don't ship total difficulty,
+/// and to cross-check the total difficulty of sources that do.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TotalDifficultyTracker {
+    next_block_number: u64,
+    total_difficulty: U256,
+}
+
+impl TotalDifficultyTracker {
+    /// Starts before the genesis block
+    pub fn genesis() -> Self {
+        TotalDifficultyTracker {
+            next_block_number: 0,
+            total_difficulty: U256::ZERO,
+        }
+    }
+
+    /// Starts after `block_number`, whose total difficulty is `total_difficulty`
+    pub fn from_checkpoint(block_number: u64, total_difficulty: U256) -> Self {
+        TotalDifficultyTracker {
+            next_block_number: block_number + 1,
+            total_difficulty,
+        }
+    }
+
+    /// The number of the block expected next
+    pub fn next_block_number(&self) -> u64 {
+        self.next_block_number
+    }
+
+    /// The total difficulty of the last block added
+    pub fn total_difficulty(&self) -> U256 {
+        self.total_difficulty
+    }
+
+    /// Adds the next header, returning its total difficulty. A header that is not the next
+    /// block is rejected and the tracker is left as it was.
+    pub fn push(&mut self, header: &Header) -> Result<U256, EraValidateError> {
+        if header.number != self.next_block_number {
+            return Err(EraValidateError::UnexpectedBlockNumber(
+                self.next_block_number,
+                header.number,
+            ));
+        }
+
+        self.total_difficulty += header.difficulty;
+        self.next_block_number += 1;
+        Ok(self.total_difficulty)
+    }
+
+    /// Adds the full header of `record`, checking that its total difficulty is the derived one
+    pub fn check(&mut self, record: &ExtHeaderRecord) -> Result<(), EraValidateError> {
+        let header = record
+            .full_header
+            .as_ref()
+            .ok_or(EraValidateError::ExtHeaderRecordError)?;
+
+        let mut next = self.clone();
+        let total_difficulty = next.push(header)?;
+        if total_difficulty != record.total_difficulty {
+            log::error!(
+                "the derived total difficulty of block {} is {} but the provided one is {}",
+                record.block_number,
+                total_difficulty,
+                record.total_difficulty
+            );
+            return Err(EraValidateError::TotalDifficultyMismatch(
+                record.block_number,
+            ));
+        }
+
+        *self = next;
+        Ok(())
+    }
+
+    /// Adds `header`, returning its record with the derived total difficulty
+    pub fn record(&mut self, header: Header) -> Result<ExtHeaderRecord, EraValidateError> {
+        let total_difficulty = self.push(&header)?;
+        Ok(ExtHeaderRecord::from_header(header, total_difficulty))
+    }
+
+    /// Adds the header of `block`, returning its record with the derived total difficulty.
+    ///
+    /// Unlike the conversion of a [`Block`] into an [`ExtHeaderRecord`], this does not need the
+    /// block to carry its total difficulty.
+    pub fn record_block(&mut self, block: &Block) -> Result<ExtHeaderRecord, EraValidateError> {
+        self.record(Header::try_from(block)?)
+    }
+}
+
+/// Checks the total difficulty of every record against the one derived from `tracker`, which
+/// can then check the next range.
+pub fn check_total_difficulty(
+    headers: &[ExtHeaderRecord],
+    tracker: &mut TotalDifficultyTracker,
+) -> Result<(), EraValidateError> {
+    headers.iter().try_for_each(|header| tracker.check(header))
+}
+
+/// Builds records for `headers` with the total difficulty derived from `tracker`, for sources
+/// that don't provide it.
+pub fn fill_total_difficulty(
+    headers: Vec<Header>,
+    tracker: &mut TotalDifficultyTracker,
+) -> Result<Vec<ExtHeaderRecord>, EraValidateError> {
+    headers
+        .into_iter()
+        .map(|header| tracker.record(header))
+        .collect()
+}
@@ -0,0 +1,143 @@
+use alloy_primitives::{b256, keccak256, B256};
+use rlp::RlpStream;
+
+/// Root of a trie without any entry, i.e. the hash of the RLP of an empty string
+pub const EMPTY_TRIE_ROOT: B256 =
+    b256!("56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421");
+
+/// Computes the root of the Merkle Patricia trie mapping the RLP of each index to its value,
+/// as used for the transactions and receipts of a block.
+pub fn ordered_trie_root<V: AsRef<[u8]>>(values: &[V]) -> B256 {
+    trie_root(
+        values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (rlp::encode(&(index as u64)).to_vec(), value.as_ref())),
+    )
+}
+
+/// Computes the root of the Merkle Patricia trie holding `entries`. Keys must be unique.
+pub fn trie_root<'a, K: AsRef<[u8]>>(entries: impl IntoIterator<Item = (K, &'a [u8])>) -> B256 {
+    let mut entries: Vec<(Vec<u8>, &[u8])> = entries
+        .into_iter()
+        .map(|(key, value)| (to_nibbles(key.as_ref()), value))
+        .collect();
+    if entries.is_empty() {
+        return EMPTY_TRIE_ROOT;
+    }
+
+    entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    keccak256(encode_node(&entries, 0))
+}
+
+/// Returns the RLP of the node holding `entries`, whose keys share their first `depth` nibbles
+fn encode_node(entries: &[(Vec<u8>, &[u8])], depth: usize) -> Vec<u8> {
+    let mut stream = RlpStream::new();
+
+    if let [(key, value)] = entries {
+        stream.begin_list(2);
+        stream.append(&hex_prefix(&key[depth..], true));
+        stream.append(value);
+        return stream.out().to_vec();
+    }
+
+    // entries are sorted, so the first and last keys share the prefix of all the keys
+    let first = &entries[0].0[depth..];
+    let last = &entries[entries.len() - 1].0[depth..];
+    let shared = first.iter().zip(last).take_while(|(a, b)| a == b).count();
+    if shared > 0 {
+        stream.begin_list(2);
+        stream.append(&hex_prefix(&first[..shared], false));
+        append_child(&mut stream, encode_node(entries, depth + shared));
+        return stream.out().to_vec();
+    }
+
+    // a key ending at this node sorts first and is the value of the branch
+    let (value, children) = match entries.split_first() {
+        Some(((key, value), children)) if key.len() == depth => (Some(*value), children),
+        _ => (None, entries),
+    };
+
+    stream.begin_list(17);
+    let mut rest = children;
+    for nibble in 0..16u8 {
+        let count = rest
+            .iter()
+            .take_while(|(key, _)| key[depth] == nibble)
+            .count();
+        let (group, remaining) = rest.split_at(count);
+        if group.is_empty() {
+            stream.append_empty_data();
+        } else {
+            append_child(&mut stream, encode_node(group, depth + 1));
+        }
+        rest = remaining;
+    }
+    match value {
+        Some(value) => stream.append(&value),
+        None => stream.append_empty_data(),
+    };
+    stream.out().to_vec()
+}
+
+/// Nodes shorter than a hash are embedded in their parent, others are referenced by hash
+fn append_child(stream: &mut RlpStream, node: Vec<u8>) {
+    if node.len() < 32 {
+        stream.append_raw(&node, 1);
+    } else {
+        stream.append(&keccak256(node).as_slice());
+    }
+}
+
+fn to_nibbles(key: &[u8]) -> Vec<u8> {
+    key.iter()
+        .flat_map(|byte| [byte >> 4, byte & 0x0f])
+        .collect()
+}
+
+/// Compacts a path of nibbles, flagging its parity and whether it ends at a leaf
+fn hex_prefix(nibbles: &[u8], leaf: bool) -> Vec<u8> {
+    let flag = if leaf { 2 } else { 0 };
+    let mut bytes = Vec::with_capacity(nibbles.len() / 2 + 1);
+    let pairs = if nibbles.len() % 2 == 1 {
+        bytes.push(((flag + 1) << 4) | nibbles[0]);
+        &nibbles[1..]
+    } else {
+        bytes.push(flag << 4);
+        nibbles
+    };
+    bytes.extend(pairs.chunks_exact(2).map(|pair| (pair[0] << 4) | pair[1]));
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_trie_root() {
+        assert_eq!(EMPTY_TRIE_ROOT, keccak256([0x80]));
+        assert_eq!(ordered_trie_root::<Vec<u8>>(&[]), EMPTY_TRIE_ROOT);
+    }
+
+    #[test]
+    fn test_trie_root() {
+        // vectors of the ethereum/tests trie tests
+        let entries: [(&str, &[u8]); 4] = [
+            ("do", b"verb"),
+            ("horse", b"stallion"),
+            ("doge", b"coin"),
+            ("dog", b"puppy"),
+        ];
+        assert_eq!(
+            trie_root(entries),
+            b256!("5991bb8c6514148a29db676a14ac506cd2cd5775ace63c30a4fe457715e9ac84")
+        );
+
+        let value = [b'a'; 50];
+        assert_eq!(
+            trie_root([("A", value.as_slice())]),
+            b256!("d23786fb4a010da3ce639d66d5e904a11dbc02746d1ce25029e53290cabf28ab")
+        );
+    }
+}
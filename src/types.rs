@@ -3,6 +3,8 @@ use alloy_primitives::B256;
 use ethportal_api::{types::execution::accumulator::HeaderRecord, Header};
 use sf_protos::ethereum::r#type::v2::Block;
 use sf_protos::ethereum::r#type::v2::BlockHeader;
+use tree_hash::TreeHash;
+use trin_validation::accumulator::PreMergeAccumulator;
 
 use crate::errors::EraValidateError;
 
@@ -14,6 +16,34 @@ pub struct ExtHeaderRecord {
     pub full_header: Option<Header>,
 }
 
+impl ExtHeaderRecord {
+    /// Builds the record of `header` from its hash, for sources that provide the total
+    /// difficulty separately, see [`crate::total_difficulty::TotalDifficultyTracker`].
+    pub fn from_header(header: Header, total_difficulty: Uint<256, 4>) -> Self {
+        ExtHeaderRecord {
+            block_hash: header.hash(),
+            total_difficulty,
+            block_number: header.number,
+            full_header: Some(header),
+        }
+    }
+
+    /// Checks that `block_hash` is the hash of `full_header`, i.e. the keccak256 of its RLP
+    /// encoding, so a corrupted header is caught before validating its whole epoch.
+    pub fn verify_hash(&self) -> Result<(), EraValidateError> {
+        let header = self
+            .full_header
+            .as_ref()
+            .ok_or(EraValidateError::ExtHeaderRecordError)?;
+
+        if header.hash() != self.block_hash {
+            return Err(EraValidateError::BlockHashMismatch(self.block_number));
+        }
+
+        Ok(())
+    }
+}
+
 impl From<ExtHeaderRecord> for HeaderRecord {
     fn from(
         ExtHeaderRecord {
@@ -71,3 +101,9 @@ impl TryFrom<&Block> for ExtHeaderRecord {
         })
     }
 }
+
+/// Identifies a [`PreMergeAccumulator`] by its SSZ hash tree root, so artifacts produced against
+/// one accumulator can be matched to it later.
+pub fn pre_merge_accumulator_id(pre_merge_accumulator: &PreMergeAccumulator) -> B256 {
+    B256::from(pre_merge_accumulator.tree_hash_root().0)
+}
@@ -0,0 +1,330 @@
+mod common;
+
+use alloy_primitives::{b256, hex, keccak256, B256};
+use common::read_blocks;
+use header_accumulator::{
+    body::{
+        encode_receipt, encode_transaction, receipts_root, transactions_root, uncles_hash,
+        verify_body, verify_uncles, MAINNET_CHAIN_ID,
+    },
+    errors::EraValidateError,
+    trie::EMPTY_TRIE_ROOT,
+};
+use rlp::Rlp;
+use sf_protos::ethereum::r#type::v2::{
+    AccessTuple, BigInt, Log, TransactionReceipt, TransactionTrace,
+};
+
+const EMPTY_UNCLES_HASH: B256 =
+    b256!("1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347");
+
+#[test]
+fn test_verify_body() {
+    let blocks = read_blocks();
+
+    for block in &blocks {
+        verify_body(block, MAINNET_CHAIN_ID).unwrap();
+    }
+
+    // the first blocks don't have any transaction
+    assert_eq!(
+        transactions_root(&blocks[0], MAINNET_CHAIN_ID).unwrap(),
+        EMPTY_TRIE_ROOT
+    );
+    assert_eq!(receipts_root(&blocks[0]).unwrap(), EMPTY_TRIE_ROOT);
+}
+
+#[test]
+fn test_verify_body_tampered() {
+    let blocks = read_blocks();
+
+    let mut tampered = blocks[100].clone();
+    tampered.transaction_traces.push(TransactionTrace {
+        receipt: Some(TransactionReceipt::default()),
+        ..Default::default()
+    });
+    assert!(matches!(
+        verify_body(&tampered, MAINNET_CHAIN_ID),
+        Err(EraValidateError::TransactionsRootMismatch(100))
+    ));
+
+    let mut tampered = blocks[100].clone();
+    tampered.header.as_mut().unwrap().receipt_root = B256::ZERO.to_vec();
+    assert!(matches!(
+        verify_body(&tampered, MAINNET_CHAIN_ID),
+        Err(EraValidateError::ReceiptsRootMismatch(100))
+    ));
+
+    let mut tampered = blocks[100].clone();
+    tampered.transaction_traces.push(TransactionTrace {
+        r#type: 100,
+        ..Default::default()
+    });
+    assert!(matches!(
+        verify_body(&tampered, MAINNET_CHAIN_ID),
+        Err(EraValidateError::UnsupportedTransactionType(100, 100))
+    ));
+
+    let mut tampered = blocks[100].clone();
+    tampered
+        .transaction_traces
+        .push(TransactionTrace::default());
+    assert!(matches!(
+        receipts_root(&tampered),
+        Err(EraValidateError::MissingReceipt(100))
+    ));
+}
+
+#[test]
+fn test_verify_uncles() {
+    let blocks = read_blocks();
+
+    for block in &blocks {
+        verify_uncles(block).unwrap();
+    }
+    assert_eq!(uncles_hash(&blocks[0]).unwrap(), EMPTY_UNCLES_HASH);
+
+    let block = blocks
+        .iter()
+        .find(|block| !block.uncles.is_empty())
+        .expect("the first blocks include uncles");
+
+    let mut tampered = block.clone();
+    tampered.uncles[0].nonce += 1;
+    assert!(matches!(
+        verify_uncles(&tampered),
+        Err(EraValidateError::UnclesHashMismatch(number)) if number == block.number
+    ));
+
+    let mut tampered = block.clone();
+    tampered.uncles[0].number = block.number;
+    assert!(matches!(
+        verify_uncles(&tampered),
+        Err(EraValidateError::InvalidUncleDepth(number, uncle)) if number == block.number && uncle == block.number
+    ));
+
+    let mut tampered = block.clone();
+    tampered.uncles = vec![block.uncles[0].clone(); 3];
+    assert!(matches!(
+        verify_body(&tampered, MAINNET_CHAIN_ID),
+        Err(EraValidateError::TooManyUncles(number)) if number == block.number
+    ));
+}
+
+fn big_int(bytes: &[u8]) -> Option<BigInt> {
+    Some(BigInt {
+        bytes: bytes.to_vec(),
+    })
+}
+
+#[test]
+fn test_encode_legacy_transaction() {
+    // the first transaction of mainnet, in block 46147
+    let trace = TransactionTrace {
+        nonce: 0,
+        gas_price: big_int(&hex!("2d79883d2000")),
+        gas_limit: 21_000,
+        to: hex!("5df9b87991262f6ba471f09758cde1c0fc1de734").to_vec(),
+        value: big_int(&hex!("7a69")),
+        v: vec![0x1c],
+        r: hex!("88ff6cf0fefd94db46111149ae4bfc179e9b94721fffd821d38d16464b3f71d0").to_vec(),
+        s: hex!("45e0aff800961cfce805daef7016b9b675c137a6a41a548f7b60a3484c06a33a").to_vec(),
+        ..Default::default()
+    };
+    let encoded = encode_transaction(&trace, MAINNET_CHAIN_ID, 46_147).unwrap();
+    assert_eq!(
+        keccak256(encoded),
+        b256!("5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060")
+    );
+
+    // the signed transaction of the EIP-155 example, with padded integers as Firehose may give
+    let trace = TransactionTrace {
+        nonce: 9,
+        gas_price: big_int(&hex!("0004a817c800")),
+        gas_limit: 21_000,
+        to: hex!("3535353535353535353535353535353535353535").to_vec(),
+        value: big_int(&hex!("0de0b6b3a7640000")),
+        v: hex!("0025").to_vec(),
+        r: hex!("28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276").to_vec(),
+        s: hex!("67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83").to_vec(),
+        ..Default::default()
+    };
+    assert_eq!(
+        encode_transaction(&trace, MAINNET_CHAIN_ID, 0).unwrap(),
+        hex!(
+            "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"
+        )
+    );
+}
+
+#[test]
+fn test_encode_access_list_transaction() {
+    // the signed access list transaction of the go-ethereum transaction tests
+    let trace = TransactionTrace {
+        r#type: 1,
+        nonce: 3,
+        gas_price: big_int(&hex!("01")),
+        gas_limit: 25_000,
+        to: hex!("b94f5374fce5edbc8e2a8697c15331677e6ebf0b").to_vec(),
+        value: big_int(&hex!("0a")),
+        input: hex!("5544").to_vec(),
+        v: vec![1],
+        r: hex!("c9519f4f2b30335884581971573fadf60c6204f59a911df35ee8a540456b2660").to_vec(),
+        s: hex!("32f1e8e2c5dd761f9e4f88f41c8310aeaba26a8bfcdacfedfa12ec3862d37521").to_vec(),
+        ..Default::default()
+    };
+    assert_eq!(
+        encode_transaction(&trace, MAINNET_CHAIN_ID, 0).unwrap(),
+        hex!(
+            "01f8630103018261a894b94f5374fce5edbc8e2a8697c15331677e6ebf0b0a825544c001a0c9519f4f2b30335884581971573fadf60c6204f59a911df35ee8a540456b2660a032f1e8e2c5dd761f9e4f88f41c8310aeaba26a8bfcdacfedfa12ec3862d37521"
+        )
+    );
+}
+
+#[test]
+fn test_encode_dynamic_fee_transaction() {
+    // a mainnet transaction, sent by 0x001e2b7de757ba469a57bf6b23d982458a07efce
+    let trace = TransactionTrace {
+        r#type: 2,
+        nonce: 2,
+        max_priority_fee_per_gas: big_int(&hex!("3b9aca00")),
+        max_fee_per_gas: big_int(&hex!("029e7822d6")),
+        gas_limit: 39_152,
+        to: hex!("d9e1459a7a482635700cbc20bbaf52d495ab9c96").to_vec(),
+        input: hex!("1b55ba3a").to_vec(),
+        v: vec![0],
+        r: hex!("c199674fcb29f353693dd779c017823b954b3c69dffa3cd6b2a6ff7888798039").to_vec(),
+        s: hex!("28ca912de909e7e6cdef9cdcaf24c54dd8c1032946dfa1d85c206b32a9064fe8").to_vec(),
+        ..Default::default()
+    };
+    let encoded = encode_transaction(&trace, MAINNET_CHAIN_ID, 0).unwrap();
+    assert_eq!(
+        encoded,
+        hex!(
+            "02f86f0102843b9aca0085029e7822d68298f094d9e1459a7a482635700cbc20bbaf52d495ab9c9680841b55ba3ac080a0c199674fcb29f353693dd779c017823b954b3c69dffa3cd6b2a6ff7888798039a028ca912de909e7e6cdef9cdcaf24c54dd8c1032946dfa1d85c206b32a9064fe8"
+        )
+    );
+    assert_eq!(
+        keccak256(encoded),
+        b256!("ce4dc6d7a7549a98ee3b071b67e970879ff51b5b95d1c340bacd80fa1e1aab31")
+    );
+}
+
+#[test]
+fn test_encode_blob_transaction() {
+    let blob_hash = hex!("01a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d8");
+    let trace = TransactionTrace {
+        r#type: 3,
+        nonce: 1,
+        max_priority_fee_per_gas: big_int(&hex!("3b9aca00")),
+        max_fee_per_gas: big_int(&hex!("029e7822d6")),
+        gas_limit: 21_000,
+        to: hex!("d9e1459a7a482635700cbc20bbaf52d495ab9c96").to_vec(),
+        access_list: vec![AccessTuple {
+            address: hex!("d9e1459a7a482635700cbc20bbaf52d495ab9c96").to_vec(),
+            storage_keys: vec![B256::ZERO.to_vec()],
+        }],
+        blob_gas_fee_cap: big_int(&hex!("0003")),
+        blob_hashes: vec![blob_hash.to_vec()],
+        v: vec![1],
+        r: vec![2],
+        s: vec![3],
+        ..Default::default()
+    };
+    let encoded = encode_transaction(&trace, MAINNET_CHAIN_ID, 0).unwrap();
+
+    // the type, then the fields of a dynamic fee transaction, followed by the max fee per blob
+    // gas and the versioned hashes of the blobs, and the signature
+    assert_eq!(encoded[0], 3);
+    let payload = Rlp::new(&encoded[1..]);
+    assert_eq!(payload.item_count().unwrap(), 14);
+    assert_eq!(payload.val_at::<u64>(0).unwrap(), MAINNET_CHAIN_ID);
+    assert_eq!(payload.val_at::<u64>(1).unwrap(), 1);
+    assert_eq!(payload.val_at::<u64>(4).unwrap(), 21_000);
+    assert_eq!(payload.at(8).unwrap().item_count().unwrap(), 1);
+    assert_eq!(payload.val_at::<u64>(9).unwrap(), 3);
+    assert_eq!(
+        payload.list_at::<Vec<u8>>(10).unwrap(),
+        vec![blob_hash.to_vec()]
+    );
+    assert_eq!(payload.val_at::<u64>(11).unwrap(), 1);
+    assert_eq!(payload.val_at::<u64>(13).unwrap(), 3);
+}
+
+#[test]
+fn test_encode_receipt() {
+    let log = Log {
+        address: [0x11; 20].to_vec(),
+        topics: vec![[0x22; 32].to_vec()],
+        data: vec![0x01],
+    };
+    let receipt = TransactionReceipt {
+        cumulative_gas_used: 21_000,
+        logs_bloom: [0; 256].to_vec(),
+        logs: vec![log],
+        ..Default::default()
+    };
+    let encoded_bloom = [hex!("b90100").as_slice(), &[0; 256]].concat();
+    let encoded_logs = [
+        hex!("f83af83894").as_slice(),
+        &[0x11; 20],
+        &hex!("e1a0"),
+        &[0x22; 32],
+        &hex!("01"),
+    ]
+    .concat();
+
+    // pre-Byzantium receipts carry the state root after the transaction
+    let trace = TransactionTrace {
+        status: 1,
+        receipt: Some(TransactionReceipt {
+            state_root: [0x33; 32].to_vec(),
+            ..receipt.clone()
+        }),
+        ..Default::default()
+    };
+    assert_eq!(
+        encode_receipt(&trace, 0).unwrap(),
+        [
+            hex!("f90163a0").as_slice(),
+            &[0x33; 32],
+            &hex!("825208"),
+            &encoded_bloom,
+            &encoded_logs,
+        ]
+        .concat()
+    );
+
+    // later ones carry the status, and are typed like their transaction
+    let trace = TransactionTrace {
+        r#type: 2,
+        status: 1,
+        receipt: Some(receipt.clone()),
+        ..Default::default()
+    };
+    assert_eq!(
+        encode_receipt(&trace, 0).unwrap(),
+        [
+            hex!("02f9014301825208").as_slice(),
+            &encoded_bloom,
+            &encoded_logs,
+        ]
+        .concat()
+    );
+
+    // a failed transaction has an empty status
+    let trace = TransactionTrace {
+        status: 2,
+        receipt: Some(receipt),
+        ..Default::default()
+    };
+    assert_eq!(
+        encode_receipt(&trace, 0).unwrap(),
+        [
+            hex!("f9014380825208").as_slice(),
+            &encoded_bloom,
+            &encoded_logs
+        ]
+        .concat()
+    );
+}
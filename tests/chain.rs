@@ -0,0 +1,57 @@
+mod common;
+
+use alloy_primitives::B256;
+use common::read_headers;
+use header_accumulator::{
+    chain::{validate_chain, ChainValidator},
+    errors::EraValidateError,
+};
+
+#[test]
+fn test_validate_chain() {
+    let headers = read_headers();
+
+    let tip = validate_chain(&headers, None).unwrap().unwrap();
+    assert_eq!(tip, (8299, headers[8299].block_hash));
+
+    // consecutive epochs join up through the anchor
+    let anchor = validate_chain(&headers[..8192], None).unwrap();
+    assert_eq!(validate_chain(&headers[8192..], anchor).unwrap(), Some(tip));
+    assert!(matches!(
+        validate_chain(&headers[8192..], Some((8191, B256::ZERO))),
+        Err(EraValidateError::ParentHashMismatch(8192))
+    ));
+}
+
+#[test]
+fn test_validate_chain_broken() {
+    let headers = read_headers();
+
+    let mut missing = headers[..200].to_vec();
+    missing.drain(50..53);
+    assert!(matches!(
+        validate_chain(&missing, None),
+        Err(EraValidateError::MissingBlockNumbers(50, 52))
+    ));
+
+    let mut duplicate = headers[..200].to_vec();
+    duplicate.insert(50, headers[50].clone());
+    assert!(matches!(
+        validate_chain(&duplicate, None),
+        Err(EraValidateError::DuplicateBlockNumber(50))
+    ));
+
+    let mut tampered = headers[..200].to_vec();
+    tampered[50].full_header.as_mut().unwrap().parent_hash = B256::ZERO;
+    assert!(matches!(
+        validate_chain(&tampered, None),
+        Err(EraValidateError::ParentHashMismatch(50))
+    ));
+
+    // a rejected header leaves the chain as it was
+    let mut validator = ChainValidator::new();
+    validator.push(&headers[0]).unwrap();
+    assert!(validator.push(&headers[2]).is_err());
+    validator.push(&headers[1]).unwrap();
+    assert_eq!(validator.tip(), Some((1, headers[1].block_hash)));
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use std::{fs, process::Command};
+
+use common::encode_blocks;
+use decoder::decode_flat_files;
+use header_accumulator::epoch::MERGE_BLOCK;
+use sf_protos::ethereum::r#type::v2::BigInt;
+use tempfile::tempdir;
+
+const FLAT_FILE: &str = "tests/ethereum_firehose_first_8200/0000000000.dbin";
+
+#[test]
+fn test_verify_seal_skips_unsealed_blocks() {
+    let dir = tempdir().unwrap();
+    fs::copy(FLAT_FILE, dir.path().join("0000000000.dbin")).unwrap();
+
+    // a post-merge block, whose header is not sealed by proof of work
+    let mut block =
+        decode_flat_files(FLAT_FILE.to_string(), None, None, Some(false)).unwrap()[1].clone();
+    block.number = MERGE_BLOCK;
+    let header = block.header.as_mut().unwrap();
+    header.number = MERGE_BLOCK;
+    header.difficulty = Some(BigInt { bytes: vec![0] });
+
+    // the blocks follow the dbin header of the test data
+    let dbin_header = &fs::read(FLAT_FILE).unwrap()[..10];
+    fs::write(
+        dir.path().join(format!("{:010}.dbin", MERGE_BLOCK)),
+        [dbin_header, &encode_blocks(&[block])].concat(),
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_header_accumulator"))
+        .arg("verify_seal")
+        .arg(dir.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    // the genesis block and the post-merge block are skipped rather than failing
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("Skipped 2 blocks not sealed by proof of work"));
+    assert!(stdout.contains("Seals verified!"));
+}
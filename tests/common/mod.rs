@@ -0,0 +1,42 @@
+#![allow(dead_code)]
+
+use decoder::decode_flat_files;
+use header_accumulator::types::ExtHeaderRecord;
+use prost::Message;
+use sf_protos::{bstream::v1::Block as BstreamBlock, ethereum::r#type::v2::Block};
+
+/// Decodes the blocks 0 to 8299 of `tests/ethereum_firehose_first_8200`
+pub fn read_blocks() -> Vec<Block> {
+    let mut blocks = Vec::new();
+    for number in (0..=8200).step_by(100) {
+        let file_name = format!("tests/ethereum_firehose_first_8200/{:010}.dbin", number);
+        blocks.extend(decode_flat_files(file_name, None, None, Some(false)).unwrap());
+    }
+    blocks
+}
+
+/// Decodes the headers of the blocks returned by [`read_blocks`]
+pub fn read_headers() -> Vec<ExtHeaderRecord> {
+    read_blocks()
+        .iter()
+        .map(|block| ExtHeaderRecord::try_from(block).unwrap())
+        .collect()
+}
+
+/// Encodes `blocks` as length-prefixed bstream blocks, without the dbin header
+pub fn encode_blocks(blocks: &[Block]) -> Vec<u8> {
+    blocks
+        .iter()
+        .flat_map(|block| {
+            let message = BstreamBlock {
+                payload_buffer: block.encode_to_vec(),
+                ..Default::default()
+            }
+            .encode_to_vec();
+            (message.len() as u32)
+                .to_be_bytes()
+                .into_iter()
+                .chain(message)
+        })
+        .collect()
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use alloy_primitives::U256;
+use common::read_headers;
+use header_accumulator::{
+    consensus::{
+        check_header_rules, expected_difficulty, validate_header_rules, HeaderRuleValidator,
+    },
+    errors::EraValidateError,
+};
+
+#[test]
+fn test_validate_header_rules() {
+    let headers = read_headers();
+
+    validate_header_rules(&headers, None).unwrap();
+
+    // consecutive epochs join up through the parent
+    let parent = headers[8191].full_header.clone();
+    validate_header_rules(&headers[8192..], parent).unwrap();
+
+    let genesis = headers[0].full_header.as_ref().unwrap();
+    let block_1 = headers[1].full_header.as_ref().unwrap();
+    assert_eq!(
+        expected_difficulty(genesis, 1, block_1.timestamp),
+        block_1.difficulty
+    );
+}
+
+#[test]
+fn test_header_rules_broken() {
+    let headers = read_headers();
+    let parent = headers[99].full_header.as_ref().unwrap();
+    let header = headers[100].full_header.as_ref().unwrap();
+
+    let mut tampered = header.clone();
+    tampered.difficulty += U256::from(1);
+    assert!(matches!(
+        check_header_rules(parent, &tampered),
+        Err(EraValidateError::InvalidDifficulty(100))
+    ));
+
+    let mut tampered = header.clone();
+    tampered.timestamp = parent.timestamp;
+    assert!(matches!(
+        check_header_rules(parent, &tampered),
+        Err(EraValidateError::InvalidTimestamp(100))
+    ));
+
+    let mut tampered = header.clone();
+    tampered.extra_data = vec![0; 33];
+    assert!(matches!(
+        check_header_rules(parent, &tampered),
+        Err(EraValidateError::InvalidExtraData(100))
+    ));
+
+    let mut tampered = header.clone();
+    tampered.gas_limit = parent.gas_limit * U256::from(2);
+    assert!(matches!(
+        check_header_rules(parent, &tampered),
+        Err(EraValidateError::InvalidGasLimit(100))
+    ));
+
+    let mut tampered = header.clone();
+    tampered.gas_used = header.gas_limit + U256::from(1);
+    assert!(matches!(
+        check_header_rules(parent, &tampered),
+        Err(EraValidateError::InvalidGasUsed(100))
+    ));
+
+    let mut tampered = header.clone();
+    tampered.base_fee_per_gas = Some(U256::from(1));
+    assert!(matches!(
+        check_header_rules(parent, &tampered),
+        Err(EraValidateError::InvalidBaseFee(100))
+    ));
+
+    // a rejected header leaves the validator as it was
+    let mut validator = HeaderRuleValidator::new();
+    validator.push(&headers[0]).unwrap();
+    assert!(validator.push(&headers[2]).is_err());
+    validator.push(&headers[1]).unwrap();
+}
@@ -1,17 +1,23 @@
-use std::fs;
+mod common;
 
+use common::read_headers;
 use decoder::decode_flat_files;
+use ethportal_api::types::execution::accumulator::{EpochAccumulator, HeaderRecord};
 use header_accumulator::{
-    era_validator::EraValidator, errors::HeaderAccumulatorError, types::ExtHeaderRecord,
+    epoch::{epoch_block_range, pre_merge_epoch_size, FINAL_EPOCH, MAX_EPOCH_SIZE, MERGE_BLOCK},
+    era_validator::{EpochStatus, EraValidateConfig, EraValidator, StreamingEraValidator},
+    errors::{EraValidateError, HeaderAccumulatorError, SyncError},
+    sync::Lock,
+    types::ExtHeaderRecord,
 };
+use tempfile::tempdir;
+use tree_hash::TreeHash;
 use trin_validation::accumulator::PreMergeAccumulator;
 
 #[test]
 fn test_era_validate() -> Result<(), HeaderAccumulatorError> {
-    // clean up before tests
-    if let Err(e) = fs::remove_file("lockfile.json") {
-        eprintln!("Error deleting lockfile.json: {}", e);
-    }
+    let dir = tempdir().unwrap();
+    let lock_config = EraValidateConfig::new().with_lockfile(dir.path().join("lockfile.json"));
 
     let mut headers: Vec<ExtHeaderRecord> = Vec::new();
     for number in (0..=8200).step_by(100) {
@@ -20,8 +26,7 @@ fn test_era_validate() -> Result<(), HeaderAccumulatorError> {
             Ok(blocks) => {
                 let (successful_headers, _): (Vec<_>, Vec<_>) = blocks
                     .iter()
-                    .cloned()
-                    .map(|block| ExtHeaderRecord::try_from(&block))
+                    .map(ExtHeaderRecord::try_from)
                     .fold((Vec::new(), Vec::new()), |(mut succ, mut errs), res| {
                         match res {
                             Ok(header) => succ.push(header),
@@ -46,38 +51,37 @@ fn test_era_validate() -> Result<(), HeaderAccumulatorError> {
     assert_eq!(headers[0].block_number, 0);
     let premerge_accumulator = PreMergeAccumulator::default();
 
-    let result = premerge_accumulator.era_validate(headers.clone(), 0, None, false)?;
+    let result = premerge_accumulator.era_validate(
+        headers[..8192].to_vec(),
+        0,
+        None,
+        &EraValidateConfig::default(),
+    )?;
     println!("result 1: {:?}", result);
 
     assert!(result.contains(&0), "The vector does not contain 0");
 
     // Test with creating a lockfile
-    let result = premerge_accumulator.era_validate(headers.clone(), 0, None, true)?;
+    let result =
+        premerge_accumulator.era_validate(headers[..8192].to_vec(), 0, None, &lock_config)?;
     println!("result 2: {:?}", result);
 
     assert!(result.contains(&0), "The vector does not contain 0");
 
     // test with the lockfile created before.
-    let result = premerge_accumulator.era_validate(headers.clone(), 0, None, true)?;
+    let result =
+        premerge_accumulator.era_validate(headers[..8192].to_vec(), 0, None, &lock_config)?;
 
     // already validated epochs are not included in the array.
     assert_eq!(result.len(), 0);
-
-    // clean up after tests
-    if let Err(e) = fs::remove_file("lockfile.json") {
-        eprintln!("Error deleting lockfile.json: {}", e);
-    }
-
     Ok(())
 }
 
 #[test]
 
 fn test_era_validate_compressed() -> Result<(), HeaderAccumulatorError> {
-    // clean up before tests
-    if let Err(e) = fs::remove_file("lockfile.json") {
-        eprintln!("Error deleting lockfile.json: {}", e);
-    }
+    let dir = tempdir().unwrap();
+    let lock_config = EraValidateConfig::new().with_lockfile(dir.path().join("lockfile.json"));
 
     let mut headers: Vec<ExtHeaderRecord> = Vec::new();
     for number in (0..=8200).step_by(100) {
@@ -86,8 +90,7 @@ fn test_era_validate_compressed() -> Result<(), HeaderAccumulatorError> {
             Ok(blocks) => {
                 let (successful_headers, _): (Vec<_>, Vec<_>) = blocks
                     .iter()
-                    .cloned()
-                    .map(|block| ExtHeaderRecord::try_from(&block))
+                    .map(ExtHeaderRecord::try_from)
                     .fold((Vec::new(), Vec::new()), |(mut succ, mut errs), res| {
                         match res {
                             Ok(header) => succ.push(header),
@@ -114,28 +117,382 @@ fn test_era_validate_compressed() -> Result<(), HeaderAccumulatorError> {
 
     let premerge_accumulator = PreMergeAccumulator::default();
 
-    let result = premerge_accumulator.era_validate(headers.clone(), 0, None, false)?;
+    let result = premerge_accumulator.era_validate(
+        headers[..8192].to_vec(),
+        0,
+        None,
+        &EraValidateConfig::default(),
+    )?;
     println!("result 1: {:?}", result);
 
     assert!(result.contains(&0), "The vector does not contain 0");
 
     // Test with creating a lockfile
-    let result = premerge_accumulator.era_validate(headers.clone(), 0, None, true)?;
+    let result =
+        premerge_accumulator.era_validate(headers[..8192].to_vec(), 0, None, &lock_config)?;
     println!("result 2: {:?}", result);
 
     assert!(result.contains(&0), "The vector does not contain 0");
 
     // test with the lockfile created before.
 
-    let result = premerge_accumulator.era_validate(headers.clone(), 0, None, true)?;
+    let result =
+        premerge_accumulator.era_validate(headers[..8192].to_vec(), 0, None, &lock_config)?;
 
     // already validated epochs are not included in the array.
     assert_eq!(result.len(), 0);
+    Ok(())
+}
+
+#[test]
+fn test_era_validate_parallel() -> Result<(), HeaderAccumulatorError> {
+    let dir = tempdir().unwrap();
+    let lockfile = dir.path().join("lockfile.json");
+    let config = EraValidateConfig::new()
+        .with_lockfile(&lockfile)
+        .with_workers(4);
+
+    let headers = read_headers();
+
+    let premerge_accumulator = PreMergeAccumulator::default();
+
+    // epoch 1 is incomplete, which fails before any epoch is validated or stored
+    let result = premerge_accumulator.era_validate(headers.clone(), 0, Some(2), &config);
+    assert!(matches!(
+        result,
+        Err(HeaderAccumulatorError::EraValidateError(
+            EraValidateError::UnexpectedHeaderCount(16384, 8300)
+        ))
+    ));
+    let lock = Lock::from_file(&lockfile).unwrap();
+    assert!(lock.get(0).unwrap().is_none());
+    assert!(lock.get(1).unwrap().is_none());
+
+    let result = premerge_accumulator.era_validate(headers[..8192].to_vec(), 0, None, &config)?;
+    assert_eq!(result, vec![0]);
+    let lock = Lock::from_file(&lockfile).unwrap();
+    assert!(lock.get(0).unwrap().is_some());
+    assert!(lock.get(1).unwrap().is_none());
+
+    let result = premerge_accumulator.era_validate(headers[..8192].to_vec(), 0, None, &config)?;
+    assert!(result.is_empty());
+
+    // the synced epoch 0 does not leave its headers to epoch 1
+    let result = premerge_accumulator.era_validate(headers.clone(), 0, Some(2), &config);
+    assert!(matches!(
+        result,
+        Err(HeaderAccumulatorError::EraValidateError(
+            EraValidateError::UnexpectedHeaderCount(16384, 8300)
+        ))
+    ));
+    assert!(Lock::from_file(&lockfile)
+        .unwrap()
+        .get(1)
+        .unwrap()
+        .is_none());
+
+    Ok(())
+}
+
+/// Relabels the headers of epoch 0 as the first headers of `epoch`. They hold the same header
+/// records, so an accumulator recording their root for `epoch` accepts them.
+fn relabelled_epoch_0(epoch: usize, headers: usize) -> (Vec<ExtHeaderRecord>, [u8; 32]) {
+    let first_block = *epoch_block_range(epoch).start();
+    let headers: Vec<ExtHeaderRecord> = read_headers()
+        .drain(..headers)
+        .map(|mut header| {
+            header.block_number += first_block;
+            header
+        })
+        .collect();
+    let root = EpochAccumulator::from(headers.iter().map(HeaderRecord::from).collect::<Vec<_>>())
+        .tree_hash_root()
+        .0;
+    (headers, root)
+}
 
-    // clean up after tests
-    if let Err(e) = fs::remove_file("lockfile.json") {
-        eprintln!("Error deleting lockfile.json: {}", e);
+#[test]
+fn test_era_validate_final_epoch() -> Result<(), HeaderAccumulatorError> {
+    let final_epoch_size = pre_merge_epoch_size(FINAL_EPOCH);
+    let (headers, root) = relabelled_epoch_0(FINAL_EPOCH, final_epoch_size);
+    let mut premerge_accumulator = PreMergeAccumulator::default();
+    premerge_accumulator.historical_epochs[FINAL_EPOCH] = root.into();
+    let config = EraValidateConfig::new();
+
+    // the final epoch ends before the merge
+    let result = premerge_accumulator.era_validate(headers.clone(), FINAL_EPOCH, None, &config)?;
+    assert_eq!(result, vec![FINAL_EPOCH]);
+    assert_eq!(
+        premerge_accumulator.process_headers(headers.clone(), FINAL_EPOCH)?,
+        root
+    );
+
+    // post-merge headers are ignored when processing the final epoch
+    let (whole_epoch, _) = relabelled_epoch_0(FINAL_EPOCH, MAX_EPOCH_SIZE);
+    assert_eq!(
+        premerge_accumulator.process_headers(whole_epoch.clone(), FINAL_EPOCH)?,
+        root
+    );
+
+    // but they are not part of the epochs to validate
+    assert!(matches!(
+        premerge_accumulator.era_validate(whole_epoch, FINAL_EPOCH, None, &config),
+        Err(HeaderAccumulatorError::EraValidateError(
+            EraValidateError::UnexpectedHeaderCount(5362, 8192)
+        ))
+    ));
+    assert!(matches!(
+        premerge_accumulator.process_headers(headers[..100].to_vec(), FINAL_EPOCH),
+        Err(HeaderAccumulatorError::EraValidateError(
+            EraValidateError::InvalidEpochLength
+        ))
+    ));
+    assert!(matches!(
+        premerge_accumulator.process_headers(headers, FINAL_EPOCH + 1),
+        Err(HeaderAccumulatorError::EraValidateError(
+            EraValidateError::EpochAfterMerge(1897)
+        ))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_era_validate_parallel_epochs() -> Result<(), HeaderAccumulatorError> {
+    let dir = tempdir().unwrap();
+    let lockfile = dir.path().join("lockfile.json");
+    let config = EraValidateConfig::new()
+        .with_lockfile(&lockfile)
+        .with_workers(4);
+
+    // relabelled copies of epoch 0 hold the same header records, so they are valid against an
+    // accumulator recording the root of epoch 0 for the following epochs
+    let epoch_0: Vec<ExtHeaderRecord> = read_headers().drain(..MAX_EPOCH_SIZE).collect();
+    let headers: Vec<ExtHeaderRecord> = (0..6)
+        .flat_map(|epoch| {
+            epoch_0.iter().cloned().map(move |mut header| {
+                header.block_number += (epoch * MAX_EPOCH_SIZE) as u64;
+                header
+            })
+        })
+        .collect();
+    let mut premerge_accumulator = PreMergeAccumulator::default();
+    for epoch in 1..6 {
+        premerge_accumulator.historical_epochs[epoch] = premerge_accumulator.historical_epochs[0];
     }
 
+    // epoch 2 is already synced, so the others are validated over two batches
+    let result = premerge_accumulator.era_validate(
+        headers[2 * MAX_EPOCH_SIZE..3 * MAX_EPOCH_SIZE].to_vec(),
+        2,
+        None,
+        &config,
+    )?;
+    assert_eq!(result, vec![2]);
+
+    let result = premerge_accumulator.era_validate(headers.clone(), 0, Some(6), &config)?;
+    assert_eq!(result, vec![0, 1, 3, 4, 5]);
+    let lock = Lock::from_file(&lockfile)?;
+    for epoch in 0..6 {
+        assert!(lock.get(epoch)?.is_some(), "epoch {} is not stored", epoch);
+    }
+
+    // an invalid epoch of the last batch fails the validation
+    premerge_accumulator.historical_epochs[5] = Default::default();
+    assert!(matches!(
+        premerge_accumulator.era_validate(
+            headers,
+            0,
+            Some(6),
+            &EraValidateConfig::new().with_workers(4)
+        ),
+        Err(HeaderAccumulatorError::EraValidateError(
+            EraValidateError::EraAccumulatorMismatch
+        ))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_era_validate_sync_error() {
+    let dir = tempdir().unwrap();
+    let headers = read_headers();
+
+    let premerge_accumulator = PreMergeAccumulator::default();
+
+    // a corrupt lockfile fails with the error of the lockfile itself
+    let lockfile = dir.path().join("lockfile.json");
+    std::fs::write(&lockfile, "not json").unwrap();
+    let config = EraValidateConfig::new().with_lockfile(&lockfile);
+    assert!(matches!(
+        premerge_accumulator.era_validate(headers[..8192].to_vec(), 0, None, &config),
+        Err(HeaderAccumulatorError::SyncError(
+            SyncError::LockfileJsonError(_)
+        ))
+    ));
+
+    // so does a lockfile that cannot be written
+    let config = EraValidateConfig::new().with_lockfile(dir.path().join("missing/lockfile.json"));
+    assert!(matches!(
+        premerge_accumulator.era_validate(headers[..8192].to_vec(), 0, None, &config),
+        Err(HeaderAccumulatorError::SyncError(
+            SyncError::LockfileIoError(_)
+        ))
+    ));
+}
+
+#[test]
+fn test_era_validate_unexpected_headers() -> Result<(), HeaderAccumulatorError> {
+    let headers = read_headers();
+
+    let premerge_accumulator = PreMergeAccumulator::default();
+    let config = EraValidateConfig::default();
+
+    // more headers than the epoch holds
+    assert!(matches!(
+        premerge_accumulator.era_validate(headers.clone(), 0, None, &config),
+        Err(HeaderAccumulatorError::EraValidateError(
+            EraValidateError::UnexpectedHeaderCount(8192, 8300)
+        ))
+    ));
+
+    // epoch 1 is incomplete
+    assert!(matches!(
+        premerge_accumulator.era_validate(headers.clone(), 0, Some(2), &config),
+        Err(HeaderAccumulatorError::EraValidateError(
+            EraValidateError::UnexpectedHeaderCount(16384, 8300)
+        ))
+    ));
+
+    // the headers do not start at the start epoch
+    assert!(matches!(
+        premerge_accumulator.era_validate(headers[..8192].to_vec(), 1, None, &config),
+        Err(HeaderAccumulatorError::EraValidateError(
+            EraValidateError::UnexpectedBlockNumber(8192, 0)
+        ))
+    ));
+
+    // a block is missing
+    let mut missing = headers[..8193].to_vec();
+    missing.remove(100);
+    assert!(matches!(
+        premerge_accumulator.era_validate(missing, 0, None, &config),
+        Err(HeaderAccumulatorError::EraValidateError(
+            EraValidateError::UnexpectedBlockNumber(100, 101)
+        ))
+    ));
+
+    // blocks are out of order
+    let mut swapped = headers[..8192].to_vec();
+    swapped.swap(10, 11);
+    assert!(matches!(
+        premerge_accumulator.era_validate(swapped, 0, None, &config),
+        Err(HeaderAccumulatorError::EraValidateError(
+            EraValidateError::UnexpectedBlockNumber(10, 11)
+        ))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_streaming_era_validator() -> Result<(), HeaderAccumulatorError> {
+    let headers = read_headers();
+
+    let premerge_accumulator = PreMergeAccumulator::default();
+
+    let mut results = StreamingEraValidator::new(&premerge_accumulator, EraValidateConfig::new())
+        .validate_iter(headers.clone());
+    let first = results.next().unwrap()?;
+    assert_eq!(first.epoch, 0);
+    assert_eq!(first.result?, EpochStatus::Validated);
+    assert!(matches!(
+        results.next(),
+        Some(Err(EraValidateError::PartialEpoch(1, 108)))
+    ));
+    assert!(results.next().is_none());
+
+    // headers must start an epoch and follow each other
+    let mut validator = StreamingEraValidator::new(&premerge_accumulator, EraValidateConfig::new());
+    assert!(matches!(
+        validator.push(headers[1].clone()),
+        Err(EraValidateError::InvalidEpochStart)
+    ));
+    assert!(validator.push(headers[0].clone())?.is_none());
+    assert!(matches!(
+        validator.push(headers[2].clone()),
+        Err(EraValidateError::UnexpectedBlockNumber(1, 2))
+    ));
+    assert_eq!(validator.buffered(), 1);
+    assert!(validator.finish().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_streaming_era_validator_final_epoch() -> Result<(), HeaderAccumulatorError> {
+    let final_epoch_size = pre_merge_epoch_size(FINAL_EPOCH);
+    let (headers, root) = relabelled_epoch_0(FINAL_EPOCH, final_epoch_size);
+    let mut premerge_accumulator = PreMergeAccumulator::default();
+    premerge_accumulator.historical_epochs[FINAL_EPOCH] = root.into();
+
+    // the final epoch is complete with the block preceding the merge
+    let mut validator = StreamingEraValidator::new(&premerge_accumulator, EraValidateConfig::new());
+    for header in &headers[..final_epoch_size - 1] {
+        assert!(validator.push(header.clone())?.is_none());
+    }
+    let last = validator
+        .push(headers[final_epoch_size - 1].clone())?
+        .expect("the final epoch is complete");
+    assert_eq!(last.epoch, FINAL_EPOCH);
+    assert_eq!(last.result?, EpochStatus::Validated);
+
+    // post-merge headers are not fed to another epoch
+    let (whole_epoch, _) = relabelled_epoch_0(FINAL_EPOCH, MAX_EPOCH_SIZE);
+    assert!(matches!(
+        validator.push(whole_epoch[final_epoch_size].clone()),
+        Err(EraValidateError::BlockAfterMerge(MERGE_BLOCK))
+    ));
+    assert_eq!(validator.buffered(), 0);
+    validator.finish()?;
+
+    // a stream ending before the merge leaves the final epoch incomplete
+    let mut results = StreamingEraValidator::new(&premerge_accumulator, EraValidateConfig::new())
+        .validate_iter(headers[..100].to_vec());
+    assert!(matches!(
+        results.next(),
+        Some(Err(EraValidateError::PartialEpoch(FINAL_EPOCH, 100)))
+    ));
+    assert_eq!(
+        EraValidateError::PartialEpoch(FINAL_EPOCH, 100).to_string(),
+        "Epoch 1896 is incomplete: 100 of 5362 headers"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_era_validate_header_hashes() -> Result<(), HeaderAccumulatorError> {
+    let mut headers = read_headers();
+    headers.truncate(8192);
+
+    let premerge_accumulator = PreMergeAccumulator::default();
+    let config = EraValidateConfig::new().with_header_hash_check(true);
+
+    assert!(headers.iter().all(|header| header.verify_hash().is_ok()));
+    assert_eq!(
+        premerge_accumulator.era_validate(headers.clone(), 0, None, &config)?,
+        vec![0]
+    );
+
+    headers[100].block_hash = headers[101].block_hash;
+    assert!(matches!(
+        premerge_accumulator.era_validate(headers, 0, None, &config),
+        Err(HeaderAccumulatorError::EraValidateError(
+            EraValidateError::BlockHashMismatch(100)
+        ))
+    ));
+
     Ok(())
 }
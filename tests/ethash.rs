@@ -0,0 +1,43 @@
+use alloy_primitives::B64;
+use decoder::decode_flat_files;
+use ethportal_api::Header;
+use header_accumulator::{errors::EraValidateError, ethash::EthashVerifier};
+
+#[test]
+fn test_verify_seal() {
+    let blocks = decode_flat_files(
+        "tests/ethereum_firehose_first_8200/0000000000.dbin".to_string(),
+        None,
+        None,
+        Some(false),
+    )
+    .unwrap();
+    let headers: Vec<Header> = blocks
+        .iter()
+        .map(|block| Header::try_from(block).unwrap())
+        .collect();
+
+    let mut verifier = EthashVerifier::new();
+    for header in &headers[1..] {
+        verifier.verify_seal(header).unwrap();
+    }
+
+    // the genesis block is not sealed by proof of work
+    assert!(matches!(
+        verifier.verify_seal(&headers[0]),
+        Err(EraValidateError::MixHashMismatch(0))
+    ));
+
+    let mut tampered = headers[5].clone();
+    tampered.nonce = Some(B64::from(1u64.to_be_bytes()));
+    assert!(matches!(
+        verifier.verify_seal(&tampered),
+        Err(EraValidateError::MixHashMismatch(5))
+    ));
+
+    tampered.nonce = None;
+    assert!(matches!(
+        verifier.verify_seal(&tampered),
+        Err(EraValidateError::MissingSeal(5))
+    ));
+}
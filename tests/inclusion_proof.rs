@@ -1,11 +1,30 @@
+mod common;
+
+use common::{read_blocks, read_headers};
 use decoder::decode_flat_files;
+use ethportal_api::{
+    types::execution::accumulator::{EpochAccumulator, HeaderRecord},
+    Header,
+};
 use header_accumulator::{
     self,
+    epoch::{
+        epoch_block_range, offset_in_epoch, pre_merge_epoch_size, FINAL_EPOCH, MAX_EPOCH_SIZE,
+        MERGE_BLOCK,
+    },
     errors::EraValidateError,
-    inclusion_proof::{generate_inclusion_proof, verify_inclusion_proof},
+    inclusion_proof::{
+        generate_inclusion_proof, generate_inclusion_proof_bundles, verify_inclusion_proof,
+        verify_inclusion_proof_bundles_report, verify_inclusion_proof_from_headers,
+        verify_inclusion_proof_report, BundleVerifier, InclusionProofBundle, ProofFailureReason,
+        INCLUSION_PROOF_BUNDLE_VERSION,
+    },
     types::ExtHeaderRecord,
 };
 use sf_protos::ethereum::r#type::v2::Block;
+use ssz::{Decode, Encode};
+use tree_hash::TreeHash;
+use trin_validation::accumulator::PreMergeAccumulator;
 
 #[test]
 fn test_inclusion_proof() -> Result<(), EraValidateError> {
@@ -55,5 +74,244 @@ fn test_inclusion_proof() -> Result<(), EraValidateError> {
     let proof_blocks: Vec<Block> = all_blocks[302..=403].to_vec();
     assert!(verify_inclusion_proof(proof_blocks, None, inclusion_proof.clone()).is_err());
 
+    // Verify inclusion proof with headers only
+    let proof_headers: Vec<Header> = all_blocks[start_block as usize..=end_block as usize]
+        .iter()
+        .map(|block| Header::try_from(block).unwrap())
+        .collect();
+    assert!(
+        verify_inclusion_proof_from_headers(proof_headers, None, inclusion_proof.clone()).is_ok()
+    );
+
+    let proof_headers: Vec<Header> = all_blocks[302..=403]
+        .iter()
+        .map(|block| Header::try_from(block).unwrap())
+        .collect();
+    assert!(
+        verify_inclusion_proof_from_headers(proof_headers, None, inclusion_proof.clone()).is_err()
+    );
+
+    // a shorter proof reports the blocks it does not cover instead of panicking
+    let proof_blocks: Vec<Block> = all_blocks[start_block as usize..=end_block as usize].to_vec();
+    let report = verify_inclusion_proof_report(&proof_blocks, None, &inclusion_proof[..50], false);
+    assert!(!report.is_valid());
+    assert_eq!(report.blocks.len(), proof_blocks.len());
+    assert!(report.blocks[..50].iter().all(|block| block.result.is_ok()));
+    assert_eq!(report.failures().count(), proof_blocks.len() - 50);
+    assert_eq!(report.blocks[50].block_number, start_block + 50);
+    assert_eq!(
+        report.blocks[50].result,
+        Err(ProofFailureReason::LengthMismatch)
+    );
+    assert!(
+        verify_inclusion_proof(proof_blocks.clone(), None, inclusion_proof[..50].to_vec()).is_err()
+    );
+
+    // fail fast stops at the first failing block
+    let report = verify_inclusion_proof_report(&proof_blocks, None, &inclusion_proof[..50], true);
+    assert_eq!(report.blocks.len(), 51);
+
+    // every block of a shifted range fails with a proof mismatch
+    let proof_blocks: Vec<Block> = all_blocks[302..=403].to_vec();
+    let report = verify_inclusion_proof_report(&proof_blocks, None, &inclusion_proof, false);
+    assert_eq!(report.failures().count(), proof_blocks.len());
+    assert!(report
+        .failures()
+        .all(|block| block.result == Err(ProofFailureReason::ProofMismatch)));
+
     Ok(())
 }
+
+#[test]
+fn test_inclusion_proof_range_not_covered() {
+    let headers = read_headers();
+
+    // headers must start on the first block of an epoch
+    let result = generate_inclusion_proof(headers[1..].to_vec(), 301, 402);
+    assert!(matches!(result, Err(EraValidateError::InvalidEpochStart)));
+
+    // epoch 1 is only partially present in the test data
+    let result = generate_inclusion_proof(headers, 8193, 8199);
+    assert!(matches!(
+        result,
+        Err(EraValidateError::BlockRangeNotCovered(8193, 8199))
+    ));
+}
+
+/// Relabels the first `count` headers of epoch 0 as headers of `epoch`, rehashing them so that
+/// their records match their headers
+fn relabelled_headers(epoch: usize, count: usize) -> Vec<ExtHeaderRecord> {
+    let first_block = *epoch_block_range(epoch).start();
+    read_headers()
+        .drain(..count)
+        .map(|mut header| {
+            let full_header = header.full_header.as_mut().unwrap();
+            full_header.number += first_block;
+            header.block_number = full_header.number;
+            header.block_hash = full_header.hash();
+            header
+        })
+        .collect()
+}
+
+/// Returns the default accumulator with the root of `headers` as the one of `epoch`
+fn accumulator_with_epoch(epoch: usize, headers: &[ExtHeaderRecord]) -> PreMergeAccumulator {
+    let mut pre_merge_accumulator = PreMergeAccumulator::default();
+    pre_merge_accumulator.historical_epochs[epoch] =
+        EpochAccumulator::from(headers.iter().map(HeaderRecord::from).collect::<Vec<_>>())
+            .tree_hash_root()
+            .0
+            .into();
+    pre_merge_accumulator
+}
+
+#[test]
+fn test_inclusion_proof_later_epoch() {
+    // epoch 0 relabelled as epoch 1, so that the headers do not start at genesis
+    let relabelled = relabelled_headers(1, MAX_EPOCH_SIZE);
+
+    let start_block = MAX_EPOCH_SIZE as u64 + 301;
+    let end_block = MAX_EPOCH_SIZE as u64 + 402;
+    let inclusion_proof = generate_inclusion_proof(relabelled.clone(), start_block, end_block)
+        .expect("headers cover the range");
+    assert_eq!(
+        inclusion_proof.len(),
+        (end_block - start_block + 1) as usize
+    );
+
+    let epoch_accumulator = EpochAccumulator::from(
+        relabelled
+            .iter()
+            .map(HeaderRecord::from)
+            .collect::<Vec<_>>(),
+    );
+    let proof_headers: Vec<Header> = relabelled
+        [offset_in_epoch(start_block)..=offset_in_epoch(end_block)]
+        .iter()
+        .map(|header| header.clone().try_into().unwrap())
+        .collect();
+    for ((header, block_number), proof) in proof_headers
+        .iter()
+        .zip(start_block..=end_block)
+        .zip(&inclusion_proof)
+    {
+        assert_eq!(header.number, block_number);
+        let expected = PreMergeAccumulator::construct_proof(header, &epoch_accumulator).unwrap();
+        assert_eq!(*proof, expected);
+    }
+
+    // the proofs verify against an accumulator holding the relabelled epoch
+    assert!(verify_inclusion_proof_from_headers(
+        proof_headers,
+        Some(accumulator_with_epoch(1, &relabelled)),
+        inclusion_proof
+    )
+    .is_ok());
+
+    // blocks before and after the epoch of the headers are not covered
+    let result = generate_inclusion_proof(relabelled.clone(), 301, 402);
+    assert!(matches!(
+        result,
+        Err(EraValidateError::BlockRangeNotCovered(301, 402))
+    ));
+    let result = generate_inclusion_proof(relabelled, end_block, 2 * MAX_EPOCH_SIZE as u64);
+    assert!(matches!(
+        result,
+        Err(EraValidateError::BlockRangeNotCovered(start, 16_384)) if start == end_block
+    ));
+}
+
+#[test]
+fn test_inclusion_proof_final_epoch() {
+    // the headers of epoch 0 relabelled as the pre-merge headers of the final epoch
+    let headers = relabelled_headers(FINAL_EPOCH, pre_merge_epoch_size(FINAL_EPOCH));
+    let pre_merge_accumulator = accumulator_with_epoch(FINAL_EPOCH, &headers);
+
+    let start_block = MERGE_BLOCK - 100;
+    let end_block = MERGE_BLOCK - 1;
+    let inclusion_proof = generate_inclusion_proof(headers.clone(), start_block, end_block)
+        .expect("the pre-merge headers cover the final epoch");
+    assert_eq!(
+        inclusion_proof.len(),
+        (end_block - start_block + 1) as usize
+    );
+
+    let proof_headers: Vec<Header> = headers[offset_in_epoch(start_block)..]
+        .iter()
+        .map(|header| header.clone().try_into().unwrap())
+        .collect();
+    assert!(verify_inclusion_proof_from_headers(
+        proof_headers,
+        Some(pre_merge_accumulator),
+        inclusion_proof
+    )
+    .is_ok());
+
+    // the merge block and later ones are not part of the accumulator
+    let result = generate_inclusion_proof(headers.clone(), start_block, MERGE_BLOCK);
+    assert!(matches!(
+        result,
+        Err(EraValidateError::BlockAfterMerge(MERGE_BLOCK))
+    ));
+    let after_final_epoch = *epoch_block_range(FINAL_EPOCH + 1).start();
+    let result = generate_inclusion_proof(headers, start_block, after_final_epoch);
+    assert!(matches!(
+        result,
+        Err(EraValidateError::EpochAfterMerge(1897))
+    ));
+}
+
+#[test]
+fn test_inclusion_proof_bundles_report() {
+    let blocks = read_blocks();
+    let pre_merge_accumulator = PreMergeAccumulator::default();
+    let mut bundles =
+        generate_inclusion_proof_bundles(read_headers(), 301, 402, &pre_merge_accumulator).unwrap();
+    let verifier = BundleVerifier::new(&pre_merge_accumulator);
+
+    // bundles are matched by block number, whatever their order
+    bundles.reverse();
+    let proof_blocks = &blocks[301..=402];
+    let report = verify_inclusion_proof_bundles_report(proof_blocks, &bundles, &verifier, false);
+    assert!(report.is_valid());
+    assert_eq!(report.blocks.len(), proof_blocks.len());
+
+    // a block without a bundle fails rather than being skipped
+    bundles.retain(|bundle| bundle.block_number != 350);
+    let report = verify_inclusion_proof_bundles_report(proof_blocks, &bundles, &verifier, false);
+    assert!(!report.is_valid());
+    let failures: Vec<_> = report.failures().collect();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].block_number, 350);
+    assert_eq!(failures[0].result, Err(ProofFailureReason::MissingBundle));
+
+    // a bundle whose block is not supplied is unmatched
+    let report =
+        verify_inclusion_proof_bundles_report(&blocks[302..=402], &bundles, &verifier, false);
+    assert_eq!(report.unmatched_proofs, 1);
+    assert!(!report.is_valid());
+}
+
+#[test]
+fn test_inclusion_proof_bundle_ssz() {
+    let headers = read_headers();
+    let pre_merge_accumulator = PreMergeAccumulator::default();
+    let bundles =
+        generate_inclusion_proof_bundles(headers.clone(), 10, 10, &pre_merge_accumulator).unwrap();
+    let header: Header = headers[10].clone().try_into().unwrap();
+
+    let bundle = InclusionProofBundle::from_ssz_bytes(&bundles[0].as_ssz_bytes()).unwrap();
+    assert_eq!(bundle, bundles[0]);
+    bundle
+        .verify(header.clone(), &pre_merge_accumulator)
+        .unwrap();
+
+    // a bundle of another version decodes, but is not verified
+    let mut bytes = bundle.as_ssz_bytes();
+    bytes[0] = INCLUSION_PROOF_BUNDLE_VERSION + 1;
+    let bundle = InclusionProofBundle::from_ssz_bytes(&bytes).unwrap();
+    assert!(matches!(
+        bundle.verify(header, &pre_merge_accumulator),
+        Err(EraValidateError::UnsupportedProofBundleVersion(2))
+    ));
+}
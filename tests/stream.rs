@@ -0,0 +1,160 @@
+mod common;
+
+use std::io::Cursor;
+
+use alloy_primitives::U256;
+use common::{encode_blocks, read_blocks};
+use header_accumulator::{
+    era_validator::EraValidateConfig,
+    errors::{EraValidateError, HeaderAccumulatorError},
+    stream::{read_block, stream_era_validate},
+    total_difficulty::TotalDifficultyTracker,
+};
+use trin_validation::accumulator::PreMergeAccumulator;
+
+/// Concatenates the flat files of the test data, each with its own dbin header, as they
+/// are piped to `era_validate stream`
+fn concatenated_flat_files(directory: &str, extension: &str) -> Vec<u8> {
+    (0..=8200)
+        .step_by(100)
+        .flat_map(|number| {
+            std::fs::read(format!("{}/{:010}.{}", directory, number, extension)).unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn test_read_block() {
+    let bytes = concatenated_flat_files("tests/ethereum_firehose_first_8200", "dbin");
+
+    let mut reader = Cursor::new(&bytes);
+    let mut blocks = Vec::new();
+    while let Some(block) = read_block(&mut reader).unwrap() {
+        blocks.push(block);
+    }
+    assert_eq!(blocks.len(), 8300);
+    assert!(blocks == read_blocks());
+
+    // a stream cut in the middle of a block is an error rather than the end of the stream
+    let mut reader = Cursor::new(&bytes[..bytes.len() - 1]);
+    let result = loop {
+        match read_block(&mut reader) {
+            Ok(Some(_)) => continue,
+            result => break result,
+        }
+    };
+    assert!(matches!(result, Err(EraValidateError::FlatFileDecodeError)));
+}
+
+#[test]
+fn test_stream_era_validate() {
+    let bytes = concatenated_flat_files("tests/ethereum_firehose_first_8200", "dbin");
+    let pre_merge_accumulator = PreMergeAccumulator::default();
+
+    let mut output = Vec::new();
+    let valid = stream_era_validate(
+        &pre_merge_accumulator,
+        &EraValidateConfig::new(),
+        Cursor::new(&bytes),
+        &mut output,
+        None,
+        None,
+    )
+    .unwrap();
+    assert!(valid);
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "epoch 0: valid\nepoch 1: incomplete: 108 of 8192 headers\n"
+    );
+
+    // the blocks asked for do not complete epoch 1
+    let mut output = Vec::new();
+    let valid = stream_era_validate(
+        &pre_merge_accumulator,
+        &EraValidateConfig::new(),
+        Cursor::new(&bytes),
+        &mut output,
+        Some(8250),
+        None,
+    )
+    .unwrap();
+    assert!(!valid);
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "epoch 0: valid\nepoch 1: incomplete: 59 of 8192 headers\n"
+    );
+}
+
+#[test]
+fn test_stream_era_validate_compressed() {
+    let bytes = concatenated_flat_files("tests/compressed", "dbin.zst");
+    let reader = zstd::stream::read::Decoder::new(Cursor::new(bytes)).unwrap();
+
+    let mut output = Vec::new();
+    let valid = stream_era_validate(
+        &PreMergeAccumulator::default(),
+        &EraValidateConfig::new(),
+        reader,
+        &mut output,
+        Some(8191),
+        None,
+    )
+    .unwrap();
+    assert!(valid);
+    assert_eq!(String::from_utf8(output).unwrap(), "epoch 0: valid\n");
+}
+
+#[test]
+fn test_stream_era_validate_without_total_difficulty() {
+    let mut blocks = read_blocks();
+    for block in &mut blocks {
+        block.header.as_mut().unwrap().total_difficulty = None;
+    }
+    let bytes = encode_blocks(&blocks[..8192]);
+    let pre_merge_accumulator = PreMergeAccumulator::default();
+
+    // the total difficulty is needed to validate an epoch
+    let result = stream_era_validate(
+        &pre_merge_accumulator,
+        &EraValidateConfig::new(),
+        Cursor::new(&bytes),
+        Vec::new(),
+        None,
+        None,
+    );
+    assert!(matches!(
+        result,
+        Err(HeaderAccumulatorError::EraValidateError(
+            EraValidateError::HeaderDecodeError
+        ))
+    ));
+
+    let mut output = Vec::new();
+    let valid = stream_era_validate(
+        &pre_merge_accumulator,
+        &EraValidateConfig::new(),
+        Cursor::new(&bytes),
+        &mut output,
+        None,
+        Some(TotalDifficultyTracker::genesis()),
+    )
+    .unwrap();
+    assert!(valid);
+    assert_eq!(String::from_utf8(output).unwrap(), "epoch 0: valid\n");
+
+    // a tracker that does not expect the first block of the stream fails
+    let result = stream_era_validate(
+        &pre_merge_accumulator,
+        &EraValidateConfig::new(),
+        Cursor::new(&bytes),
+        Vec::new(),
+        None,
+        Some(TotalDifficultyTracker::from_checkpoint(100, U256::ZERO)),
+    );
+    assert!(matches!(
+        result,
+        Err(HeaderAccumulatorError::EraValidateError(
+            EraValidateError::UnexpectedBlockNumber(101, 0)
+        ))
+    ));
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use alloy_primitives::U256;
+use common::{read_blocks, read_headers};
+use ethportal_api::Header;
+use header_accumulator::{
+    era_validator::{EraValidateConfig, EraValidator},
+    errors::EraValidateError,
+    total_difficulty::{check_total_difficulty, fill_total_difficulty, TotalDifficultyTracker},
+    types::ExtHeaderRecord,
+};
+use trin_validation::accumulator::PreMergeAccumulator;
+
+#[test]
+fn test_check_total_difficulty() {
+    let headers = read_headers();
+
+    let mut tracker = TotalDifficultyTracker::genesis();
+    check_total_difficulty(&headers[..8192], &mut tracker).unwrap();
+    assert_eq!(tracker.total_difficulty(), headers[8191].total_difficulty);
+
+    // continuing from a checkpoint gives the same result
+    let mut from_checkpoint =
+        TotalDifficultyTracker::from_checkpoint(8191, headers[8191].total_difficulty);
+    assert_eq!(from_checkpoint, tracker);
+    check_total_difficulty(&headers[8192..], &mut from_checkpoint).unwrap();
+
+    let mut tampered = headers[..200].to_vec();
+    tampered[100].total_difficulty += U256::from(1);
+    assert!(matches!(
+        check_total_difficulty(&tampered, &mut TotalDifficultyTracker::genesis()),
+        Err(EraValidateError::TotalDifficultyMismatch(100))
+    ));
+}
+
+#[test]
+fn test_fill_total_difficulty() {
+    let blocks = read_blocks();
+    let expected: Vec<ExtHeaderRecord> = blocks
+        .iter()
+        .map(|block| ExtHeaderRecord::try_from(block).unwrap())
+        .collect();
+
+    // a source without total difficulty only provides the headers
+    let headers: Vec<Header> = blocks
+        .iter()
+        .take(8192)
+        .map(|block| Header::try_from(block).unwrap())
+        .collect();
+    let mut tracker = TotalDifficultyTracker::genesis();
+    let filled = fill_total_difficulty(headers, &mut tracker).unwrap();
+
+    for (filled, expected) in filled.iter().zip(&expected) {
+        assert_eq!(filled.block_hash, expected.block_hash);
+        assert_eq!(filled.total_difficulty, expected.total_difficulty);
+    }
+
+    let result = PreMergeAccumulator::default()
+        .era_validate(filled, 0, None, &EraValidateConfig::default())
+        .unwrap();
+    assert_eq!(result, vec![0]);
+}
+
+#[test]
+fn test_record_block_without_total_difficulty() {
+    let mut blocks = read_blocks();
+    let expected: Vec<ExtHeaderRecord> = blocks
+        .iter()
+        .map(|block| ExtHeaderRecord::try_from(block).unwrap())
+        .collect();
+    for block in &mut blocks {
+        block.header.as_mut().unwrap().total_difficulty = None;
+    }
+    assert!(matches!(
+        ExtHeaderRecord::try_from(&blocks[0]),
+        Err(EraValidateError::HeaderDecodeError)
+    ));
+
+    let mut tracker = TotalDifficultyTracker::genesis();
+    let records: Vec<ExtHeaderRecord> = blocks[..8192]
+        .iter()
+        .map(|block| tracker.record_block(block))
+        .collect::<Result<_, _>>()
+        .unwrap();
+    for (record, expected) in records.iter().zip(&expected) {
+        assert_eq!(record.block_hash, expected.block_hash);
+        assert_eq!(record.total_difficulty, expected.total_difficulty);
+    }
+
+    let result = PreMergeAccumulator::default()
+        .era_validate(records, 0, None, &EraValidateConfig::default())
+        .unwrap();
+    assert_eq!(result, vec![0]);
+}